@@ -0,0 +1,469 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Dense sets of `u32` implemented as immutable word-array bitmaps.
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitOr, BitXor, Range, Sub};
+
+use ord_list_set::OrdListSet;
+use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// An immutable set of `u32` stored as a bitmap (bit `i % 64` of word `i / 64` is set if `i`
+/// is a member) so that it takes one bit per possible member (up to the largest member) and
+/// set operations between bitmaps work a word (64 members) at a time.
+///
+/// # Examples
+///
+/// ```
+/// use ord_bitmap_set::BitmapSet;
+///
+/// let a = BitmapSet::from([1, 2, 3, 64, 65, 200]);
+/// let b = BitmapSet::from([2, 3, 4, 65, 300]);
+/// assert_eq!(&a | &b, BitmapSet::from([1, 2, 3, 4, 64, 65, 200, 300]));
+/// assert_eq!(&a & &b, BitmapSet::from([2, 3, 65]));
+/// assert_eq!(&a - &b, BitmapSet::from([1, 64, 200]));
+/// assert_eq!(&a ^ &b, BitmapSet::from([1, 4, 64, 200, 300]));
+/// ```
+#[derive(Clone)]
+pub struct BitmapSet {
+    // NB: no trailing zero words
+    words: Box<[u64]>,
+    // The number of members in the words before each word
+    ranks: Box<[usize]>,
+    len: usize,
+}
+
+impl BitmapSet {
+    pub fn empty_set() -> Self {
+        Self::from_words(vec![])
+    }
+
+    fn from_words(mut words: Vec<u64>) -> Self {
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+        let mut len = 0;
+        let ranks = words
+            .iter()
+            .map(|word| {
+                let rank = len;
+                len += word.count_ones() as usize;
+                rank
+            })
+            .collect();
+        Self {
+            words: words.into_boxed_slice(),
+            ranks,
+            len,
+        }
+    }
+
+    fn word(&self, index: usize) -> u64 {
+        self.words.get(index).copied().unwrap_or(0)
+    }
+
+    /// The number of members.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the set contains the specified item.
+    pub fn contains(&self, item: &u32) -> bool {
+        let item = *item as usize;
+        self.word(item / WORD_BITS) & (1 << (item % WORD_BITS)) != 0
+    }
+
+    // The members in the given range of words.
+    fn members(&self, words: Range<usize>) -> impl Iterator<Item = u32> + '_ {
+        words.flat_map(move |index| {
+            let mut bits = self.words[index];
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    None
+                } else {
+                    let bit = bits.trailing_zeros();
+                    bits &= bits - 1;
+                    Some((index * WORD_BITS) as u32 + bit)
+                }
+            })
+        })
+    }
+
+    /// An iterator over the members that can be combined with other ordered set iterators.
+    /// The members that are iterated over (as opposed to seeked past) are stored in `arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_bitmap_set::BitmapSet;
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator, ValueArena};
+    ///
+    /// let bitmap = BitmapSet::from_iter(0..10_000);
+    /// let list = OrdListSet::<u32>::from([5, 50, 500, 50_000]);
+    /// let btree = BTreeSet::<u32>::from([50, 5_000]);
+    /// let arena = ValueArena::default();
+    /// let iter = bitmap.iter_in(&arena).intersection(list.iter()).difference(btree.oso_iter());
+    /// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![5, 500]);
+    /// ```
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<u32>) -> BitmapSetIter<'a> {
+        BitmapSetIter {
+            set: self,
+            arena,
+            word: 0,
+            bits: self.word(0),
+            item: None,
+        }
+    }
+
+    /// The members (in ascending order).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_bitmap_set::BitmapSet;
+    ///
+    /// let set = BitmapSet::from([300, 2, 64]);
+    /// assert_eq!(set.values().collect::<Vec<_>>(), vec![2, 64, 300]);
+    /// ```
+    pub fn values(&self) -> impl Iterator<Item = u32> + '_ {
+        self.members(0..self.words.len())
+    }
+
+    // Combine the two bitmaps (up to `len` words) a word at a time.
+    fn combine(&self, other: &Self, len: usize, op: impl Fn(u64, u64) -> u64) -> Self {
+        Self::from_words(
+            (0..len)
+                .map(|index| op(self.word(index), other.word(index)))
+                .collect(),
+        )
+    }
+
+    /// The members of `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        let len = self.words.len().max(other.words.len());
+        self.combine(other, len, |a, b| a | b)
+    }
+
+    /// The members of both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let len = self.words.len().min(other.words.len());
+        self.combine(other, len, |a, b| a & b)
+    }
+
+    /// The members of `self` that aren't members of `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, self.words.len(), |a, b| a & !b)
+    }
+
+    /// The members of `self` or `other` but not both.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        let len = self.words.len().max(other.words.len());
+        self.combine(other, len, |a, b| a ^ b)
+    }
+}
+
+impl Default for BitmapSet {
+    fn default() -> Self {
+        Self::empty_set()
+    }
+}
+
+impl PartialEq for BitmapSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.words == other.words
+    }
+}
+
+impl Eq for BitmapSet {}
+
+impl fmt::Debug for BitmapSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.values()).finish()
+    }
+}
+
+impl FromIterator<u32> for BitmapSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut words = vec![];
+        for member in iter {
+            let (word, bit) = (member as usize / WORD_BITS, member as usize % WORD_BITS);
+            if word >= words.len() {
+                words.resize(word + 1, 0);
+            }
+            words[word] |= 1 << bit;
+        }
+        Self::from_words(words)
+    }
+}
+
+impl<const N: usize> From<[u32; N]> for BitmapSet {
+    fn from(members: [u32; N]) -> Self {
+        Self::from_iter(members)
+    }
+}
+
+impl From<&OrdListSet<u32>> for BitmapSet {
+    fn from(list: &OrdListSet<u32>) -> Self {
+        Self::from_iter(list.iter().copied())
+    }
+}
+
+impl From<&BitmapSet> for OrdListSet<u32> {
+    /// Create an OrdListSet<u32> from the members of a BitmapSet.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_bitmap_set::BitmapSet;
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let list = OrdListSet::<u32>::from([1, 2, 3, 5, 7, 800]);
+    /// let bitmap = BitmapSet::from(&list);
+    /// assert_eq!(OrdListSet::from(&bitmap), list);
+    /// ```
+    fn from(set: &BitmapSet) -> Self {
+        set.values().collect()
+    }
+}
+
+impl Sub<&BitmapSet> for &BitmapSet {
+    type Output = BitmapSet;
+
+    /// Returns the difference of `self` and `rhs` as a new `BitmapSet`.
+    fn sub(self, rhs: &BitmapSet) -> BitmapSet {
+        self.difference(rhs)
+    }
+}
+
+impl BitAnd<&BitmapSet> for &BitmapSet {
+    type Output = BitmapSet;
+
+    /// Returns the intersection of `self` and `rhs` as a new `BitmapSet`.
+    fn bitand(self, rhs: &BitmapSet) -> BitmapSet {
+        self.intersection(rhs)
+    }
+}
+
+impl BitXor<&BitmapSet> for &BitmapSet {
+    type Output = BitmapSet;
+
+    /// Returns the symmetric difference of `self` and `rhs` as a new `BitmapSet`.
+    fn bitxor(self, rhs: &BitmapSet) -> BitmapSet {
+        self.symmetric_difference(rhs)
+    }
+}
+
+impl BitOr<&BitmapSet> for &BitmapSet {
+    type Output = BitmapSet;
+
+    /// Returns the union of `self` and `rhs` as a new `BitmapSet`.
+    fn bitor(self, rhs: &BitmapSet) -> BitmapSet {
+        self.union(rhs)
+    }
+}
+
+/// An iterator over the members of a `BitmapSet`.  Seeks jump directly to the target's word.
+pub struct BitmapSetIter<'a> {
+    set: &'a BitmapSet,
+    arena: &'a ValueArena<u32>,
+    // The current word and its members that haven't been visited
+    word: usize,
+    bits: u64,
+    // The next member (once it has been stored in the arena)
+    item: Option<&'a u32>,
+}
+
+impl<'a> Clone for BitmapSetIter<'a> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            arena: self.arena,
+            word: self.word,
+            bits: self.bits,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a> BitmapSetIter<'a> {
+    // The next member (moving on to the next non empty word if necessary).
+    fn position(&mut self) -> Option<u32> {
+        while self.bits == 0 {
+            if self.word + 1 >= self.set.words.len() {
+                self.word = self.set.words.len();
+                return None;
+            }
+            self.word += 1;
+            self.bits = self.set.words[self.word];
+        }
+        Some((self.word * WORD_BITS) as u32 + self.bits.trailing_zeros())
+    }
+
+    fn remaining(&self) -> usize {
+        match self.set.words.get(self.word) {
+            Some(word) => {
+                let visited = (word.count_ones() - self.bits.count_ones()) as usize;
+                self.set.len - self.set.ranks[self.word] - visited
+            }
+            None => 0,
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.word = self.set.words.len();
+        self.bits = 0;
+        self.item = None;
+    }
+}
+
+impl<'a> Iterator for BitmapSetIter<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        self.bits &= self.bits - 1;
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> PeepAdvanceIter<'a, u32> for BitmapSetIter<'a> {
+    fn peep(&mut self) -> Option<&'a u32> {
+        if self.item.is_none() {
+            let member = self.position()?;
+            self.item = Some(self.arena.alloc(member));
+        }
+        self.item
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            let remaining = self.remaining();
+            write!(
+                f,
+                "BitmapSetIter[pos={}, remaining={}]",
+                self.set.len - remaining,
+                remaining
+            )
+        } else {
+            write!(f, "BitmapSetIter[len={}]", self.set.len)
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Advance this iterator to the next member at or after the given item.  Implementation
+    /// jumps directly to the target's word.
+    ///
+    /// Example
+    /// ```
+    /// use ord_bitmap_set::BitmapSet;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    ///
+    /// let set = BitmapSet::from([1, 7, 8, 9, 200, 3]);
+    /// let arena = ValueArena::default();
+    /// let mut iter = set.iter_in(&arena);
+    /// iter.advance_until(&3);
+    /// assert_eq!(iter.next(), Some(&3));
+    /// iter.advance_until(&10);
+    /// assert_eq!(iter.peep(), Some(&200));
+    /// iter.advance_until(&5);
+    /// assert_eq!(iter.next(), Some(&200));
+    /// iter.advance_until(&300);
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    fn advance_until(&mut self, target: &u32) {
+        // Make sure we don't go backwards
+        if let Some(member) = self.position() {
+            if member < *target {
+                let word = *target as usize / WORD_BITS;
+                if word < self.set.words.len() {
+                    self.word = word;
+                    self.bits = self.set.words[word] & (!0 << (*target as usize % WORD_BITS));
+                    self.item = None;
+                } else {
+                    self.exhaust();
+                }
+            }
+        }
+    }
+
+    fn advance_after(&mut self, target: &u32) {
+        match target.checked_add(1) {
+            Some(next) => self.advance_until(&next),
+            None => self.exhaust(),
+        }
+    }
+}
+
+impl<'a> OrdSetIterSetOpsIterator<'a, u32> for BitmapSetIter<'a> {}
+
+impl<'a> fmt::Debug for BitmapSetIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a> fmt::Display for BitmapSetIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_algebra() {
+        let a = BitmapSet::from_iter((0..1000).filter(|i| i % 3 == 0));
+        let b = BitmapSet::from_iter((0..700).filter(|i| i % 5 == 0));
+        let a_list = OrdListSet::from(&a);
+        let b_list = OrdListSet::from(&b);
+        assert_eq!(OrdListSet::from(&(&a | &b)), &a_list | &b_list);
+        assert_eq!(OrdListSet::from(&(&a & &b)), &a_list & &b_list);
+        assert_eq!(OrdListSet::from(&(&a - &b)), &a_list - &b_list);
+        assert_eq!(OrdListSet::from(&(&a ^ &b)), &a_list ^ &b_list);
+        assert!((&a ^ &a).is_empty());
+        assert_eq!((&b - &a).len(), b.len() - (&a & &b).len());
+    }
+
+    #[test]
+    fn iteration() {
+        let set = BitmapSet::from([0, 63, 64, 255, 256, 1000, 100_000]);
+        let arena = ValueArena::default();
+        assert_eq!(
+            set.iter_in(&arena).cloned().collect::<Vec<_>>(),
+            vec![0, 63, 64, 255, 256, 1000, 100_000]
+        );
+        // only the members (not the words or blocks holding them) are stored
+        assert_eq!(arena.len(), 7);
+        assert!(set.values().eq([0, 63, 64, 255, 256, 1000, 100_000]));
+        let mut iter = set.iter_in(&arena);
+        assert_eq!(iter.size_hint(), (7, Some(7)));
+        iter.advance_after(&63);
+        assert_eq!(iter.next(), Some(&64));
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        assert_eq!(format!("{iter:?}"), "BitmapSetIter[pos=3, remaining=4]");
+        iter.advance_until(&1001);
+        assert_eq!(iter.peep(), Some(&100_000));
+        iter.advance_after(&u32::MAX);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert!(set.contains(&255) && !set.contains(&254));
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! The containers holding the low 16 bits of the members of a chunk of the key space.
+
+use std::fmt;
+
+pub(crate) const CHUNK_SIZE: u32 = 1 << 16;
+const BITMAP_WORDS: usize = CHUNK_SIZE as usize / 64;
+// The size (in bytes) of a bitmap container, which is fixed.
+const BITMAP_BYTES: usize = BITMAP_WORDS * 8;
+
+/// How the members of a chunk are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    /// A sorted array of the members.
+    Array,
+    /// A bitmap with a bit for each possible member.
+    Bitmap,
+    /// A sorted list of runs of consecutive members.
+    Runs,
+}
+
+impl fmt::Display for ContainerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerKind::Array => write!(f, "array"),
+            ContainerKind::Bitmap => write!(f, "bitmap"),
+            ContainerKind::Runs => write!(f, "runs"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+impl SetOp {
+    pub(crate) fn keep(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            SetOp::Union => in_left || in_right,
+            SetOp::Intersection => in_left && in_right,
+            SetOp::Difference => in_left && !in_right,
+            SetOp::SymmetricDifference => in_left != in_right,
+        }
+    }
+
+    fn words(self, left: u64, right: u64) -> u64 {
+        match self {
+            SetOp::Union => left | right,
+            SetOp::Intersection => left & right,
+            SetOp::Difference => left & !right,
+            SetOp::SymmetricDifference => left ^ right,
+        }
+    }
+}
+
+/// The (non empty) set of the low 16 bits of the members of a chunk.  The representation is
+/// always the smallest of the three (so that equal sets have equal containers).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Container {
+    Array(Box<[u16]>),
+    Bitmap(Box<[u64]>),
+    // Inclusive (first, last) runs that are neither overlapping nor adjacent
+    Runs(Box<[(u16, u16)]>),
+}
+
+impl Container {
+    /// The smallest container for the given (sorted and deduplicated) members or `None` if
+    /// there are no members.
+    pub(crate) fn from_sorted(members: Vec<u16>) -> Option<Self> {
+        if members.is_empty() {
+            None
+        } else {
+            Some(Container::Array(members.into_boxed_slice()).optimized())
+        }
+    }
+
+    pub(crate) fn kind(&self) -> ContainerKind {
+        match self {
+            Container::Array(_) => ContainerKind::Array,
+            Container::Bitmap(_) => ContainerKind::Bitmap,
+            Container::Runs(_) => ContainerKind::Runs,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Container::Array(members) => members.len(),
+            Container::Bitmap(words) => words.iter().map(|word| word.count_ones() as usize).sum(),
+            Container::Runs(runs) => runs
+                .iter()
+                .map(|(first, last)| (last - first) as usize + 1)
+                .sum(),
+        }
+    }
+
+    fn run_count(&self) -> usize {
+        match self {
+            Container::Array(members) => {
+                1 + members
+                    .windows(2)
+                    .filter(|pair| pair[1] != pair[0] + 1)
+                    .count()
+            }
+            Container::Bitmap(words) => {
+                let mut carry = 0;
+                words
+                    .iter()
+                    .map(|word| {
+                        let starts = word & !((word << 1) | carry);
+                        carry = word >> 63;
+                        starts.count_ones() as usize
+                    })
+                    .sum()
+            }
+            Container::Runs(runs) => runs.len(),
+        }
+    }
+
+    // Convert to whichever representation is smallest.
+    fn optimized(self) -> Self {
+        let len = self.len();
+        let array_bytes = len * 2;
+        let runs_bytes = self.run_count() * 4;
+        if runs_bytes < array_bytes.min(BITMAP_BYTES) {
+            Container::Runs(self.to_runs().into_boxed_slice())
+        } else if array_bytes <= BITMAP_BYTES {
+            Container::Array(self.to_array().into_boxed_slice())
+        } else {
+            Container::Bitmap(self.to_bitmap().into_boxed_slice())
+        }
+    }
+
+    fn to_array(&self) -> Vec<u16> {
+        match self {
+            Container::Array(members) => members.to_vec(),
+            _ => self.members(0).collect(),
+        }
+    }
+
+    /// The members that aren't less than `from` in ascending order.
+    pub(crate) fn members(&self, from: u32) -> impl Iterator<Item = u16> + '_ {
+        let mut next = from;
+        std::iter::from_fn(move || {
+            let member = self.next_member(next)?;
+            next = member as u32 + 1;
+            Some(member)
+        })
+    }
+
+    fn to_bitmap(&self) -> Vec<u64> {
+        match self {
+            Container::Bitmap(words) => words.to_vec(),
+            Container::Array(members) => {
+                let mut words = vec![0; BITMAP_WORDS];
+                for member in members.iter() {
+                    words[*member as usize / 64] |= 1 << (member % 64);
+                }
+                words
+            }
+            Container::Runs(runs) => {
+                let mut words = vec![0; BITMAP_WORDS];
+                for (first, last) in runs.iter() {
+                    for member in *first as usize..=*last as usize {
+                        words[member / 64] |= 1 << (member % 64);
+                    }
+                }
+                words
+            }
+        }
+    }
+
+    fn to_runs(&self) -> Vec<(u16, u16)> {
+        match self {
+            Container::Runs(runs) => runs.to_vec(),
+            _ => {
+                let mut runs: Vec<(u16, u16)> = vec![];
+                for member in self.to_array() {
+                    match runs.last_mut() {
+                        Some((_, last)) if *last as u32 + 1 == member as u32 => *last = member,
+                        _ => runs.push((member, member)),
+                    }
+                }
+                runs
+            }
+        }
+    }
+
+    pub(crate) fn contains(&self, member: u16) -> bool {
+        self.next_member(member as u32) == Some(member)
+    }
+
+    /// The first member that isn't less than `from` (if any).
+    pub(crate) fn next_member(&self, from: u32) -> Option<u16> {
+        if from >= CHUNK_SIZE {
+            return None;
+        }
+        match self {
+            Container::Array(members) => {
+                let index = members.partition_point(|member| (*member as u32) < from);
+                members.get(index).copied()
+            }
+            Container::Bitmap(words) => {
+                let mut index = from as usize / 64;
+                let mut bits = words[index] & (!0 << (from % 64));
+                while bits == 0 {
+                    index += 1;
+                    bits = *words.get(index)?;
+                }
+                Some((index * 64) as u16 + bits.trailing_zeros() as u16)
+            }
+            Container::Runs(runs) => {
+                let index = runs.partition_point(|(_, last)| (*last as u32) < from);
+                runs.get(index)
+                    .map(|(first, _)| (*first as u32).max(from) as u16)
+            }
+        }
+    }
+
+    /// The number of members that aren't less than `from`.
+    pub(crate) fn count_from(&self, from: u32) -> usize {
+        if from >= CHUNK_SIZE {
+            return 0;
+        }
+        match self {
+            Container::Array(members) => {
+                members.len() - members.partition_point(|member| (*member as u32) < from)
+            }
+            Container::Bitmap(words) => {
+                let index = from as usize / 64;
+                (words[index] & (!0 << (from % 64))).count_ones() as usize
+                    + words[index + 1..]
+                        .iter()
+                        .map(|word| word.count_ones() as usize)
+                        .sum::<usize>()
+            }
+            Container::Runs(runs) => runs
+                .iter()
+                .filter(|(_, last)| *last as u32 >= from)
+                .map(|(first, last)| (*last as u32 - (*first as u32).max(from)) as usize + 1)
+                .sum(),
+        }
+    }
+
+    /// Combine the two containers dispatching on their kinds.  Returns `None` if the result
+    /// is empty.
+    pub(crate) fn combine(&self, other: &Self, op: SetOp) -> Option<Self> {
+        let result = match (self, other) {
+            (Container::Bitmap(_), _) | (_, Container::Bitmap(_)) => {
+                let (left, right) = (self.to_bitmap(), other.to_bitmap());
+                let words: Vec<u64> = left
+                    .iter()
+                    .zip(right.iter())
+                    .map(|(l, r)| op.words(*l, *r))
+                    .collect();
+                if words.iter().all(|word| *word == 0) {
+                    return None;
+                }
+                Container::Bitmap(words.into_boxed_slice())
+            }
+            (Container::Array(left), Container::Array(right)) => {
+                Container::Array(merge_arrays(left, right, op)?.into_boxed_slice())
+            }
+            _ => Container::Runs(
+                merge_runs(&self.to_runs(), &other.to_runs(), op)?.into_boxed_slice(),
+            ),
+        };
+        Some(result.optimized())
+    }
+}
+
+// Merge two sorted arrays keeping the members selected by `op`.
+fn merge_arrays(left: &[u16], right: &[u16], op: SetOp) -> Option<Vec<u16>> {
+    let mut members = vec![];
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let (member, in_left, in_right) = match (left.get(i), right.get(j)) {
+            (Some(l), Some(r)) if l == r => (*l, true, true),
+            (Some(l), Some(r)) if l < r => (*l, true, false),
+            (Some(l), None) => (*l, true, false),
+            (_, Some(r)) => (*r, false, true),
+            (None, None) => break,
+        };
+        if in_left {
+            i += 1;
+        }
+        if in_right {
+            j += 1;
+        }
+        if op.keep(in_left, in_right) {
+            members.push(member);
+        }
+    }
+    if members.is_empty() {
+        None
+    } else {
+        Some(members)
+    }
+}
+
+// Sweep over the boundaries of two run lists keeping the members selected by `op`.
+fn merge_runs(left: &[(u16, u16)], right: &[(u16, u16)], op: SetOp) -> Option<Vec<(u16, u16)>> {
+    // as half-open ranges
+    let boundaries = |runs: &[(u16, u16)]| -> Vec<u32> {
+        runs.iter()
+            .flat_map(|(first, last)| [*first as u32, *last as u32 + 1])
+            .collect()
+    };
+    let (left, right) = (boundaries(left), boundaries(right));
+    let (mut i, mut j) = (0, 0);
+    let mut start: Option<u32> = None;
+    let mut runs = vec![];
+    loop {
+        let point = match (left.get(i), right.get(j)) {
+            (Some(l), Some(r)) => *l.min(r),
+            (Some(l), None) => *l,
+            (None, Some(r)) => *r,
+            (None, None) => break,
+        };
+        if left.get(i) == Some(&point) {
+            i += 1;
+        }
+        if right.get(j) == Some(&point) {
+            j += 1;
+        }
+        // an odd number of boundaries passed means we're inside a run
+        match (start.take(), op.keep(i % 2 == 1, j % 2 == 1)) {
+            (None, true) => start = Some(point),
+            (Some(first), true) => start = Some(first),
+            (Some(first), false) => runs.push((first as u16, (point - 1) as u16)),
+            (None, false) => (),
+        }
+    }
+    if runs.is_empty() {
+        None
+    } else {
+        Some(runs)
+    }
+}
@@ -0,0 +1,591 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets of `u32` or `u64` that adapt their representation to the density of their members
+//! (in the style of "roaring" bitmaps).
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr, BitXor, Sub};
+
+use ord_list_set::OrdListSet;
+use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+mod container;
+
+pub use container::ContainerKind;
+use container::{Container, SetOp};
+
+/// The unsigned integer types that can be the members of a `HybridSet`.  Members are split
+/// into a chunk key (the high bits) and the low 16 bits which are stored in the chunk's
+/// container.
+pub trait HybridInt: Copy + Ord + 'static {
+    fn high(self) -> u64;
+
+    fn low(self) -> u16;
+
+    fn join(high: u64, low: u16) -> Self;
+}
+
+macro_rules! impl_hybrid_int {
+    ($($int:ty),+) => {
+        $(
+            impl HybridInt for $int {
+                fn high(self) -> u64 {
+                    self as u64 >> 16
+                }
+
+                fn low(self) -> u16 {
+                    self as u16
+                }
+
+                fn join(high: u64, low: u16) -> Self {
+                    ((high << 16) | low as u64) as $int
+                }
+            }
+        )+
+    };
+}
+
+impl_hybrid_int!(u32, u64);
+
+/// An immutable set of `u32` or `u64` that splits the key space into chunks of 2^16 possible
+/// members and stores the members of each (non empty) chunk in whichever of a sorted array, a
+/// bitmap or a list of runs is smallest.  Set operations are performed chunk by chunk using
+/// the algorithm appropriate to the two containers involved.
+///
+/// # Examples
+///
+/// ```
+/// use ord_hybrid_set::HybridSet;
+///
+/// let a = HybridSet::<u32>::from([1, 2, 3, 70_000, 70_001, 5_000_000]);
+/// let b = HybridSet::<u32>::from([2, 3, 4, 70_001, 6_000_000]);
+/// assert_eq!(&a | &b, HybridSet::from([1, 2, 3, 4, 70_000, 70_001, 5_000_000, 6_000_000]));
+/// assert_eq!(&a & &b, HybridSet::from([2, 3, 70_001]));
+/// assert_eq!(&a - &b, HybridSet::from([1, 70_000, 5_000_000]));
+/// assert_eq!(&a ^ &b, HybridSet::from([1, 4, 70_000, 5_000_000, 6_000_000]));
+/// ```
+#[derive(Clone)]
+pub struct HybridSet<T> {
+    // The (ascending) chunk keys and the corresponding containers
+    keys: Box<[u64]>,
+    containers: Box<[Container]>,
+    // The number of members in the containers before each container
+    offsets: Box<[usize]>,
+    len: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T: HybridInt> HybridSet<T> {
+    pub fn empty_set() -> Self {
+        Self::from_chunks(vec![])
+    }
+
+    fn from_chunks(chunks: Vec<(u64, Container)>) -> Self {
+        let mut len = 0;
+        let offsets = chunks
+            .iter()
+            .map(|(_, container)| {
+                let offset = len;
+                len += container.len();
+                offset
+            })
+            .collect();
+        let (keys, containers): (Vec<u64>, Vec<Container>) = chunks.into_iter().unzip();
+        Self {
+            keys: keys.into_boxed_slice(),
+            containers: containers.into_boxed_slice(),
+            offsets,
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The number of members.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the set contains the specified item.
+    pub fn contains(&self, item: &T) -> bool {
+        match self.keys.binary_search(&item.high()) {
+            Ok(index) => self.containers[index].contains(item.low()),
+            Err(_) => false,
+        }
+    }
+
+    /// The kind of container used for each (non empty) chunk in ascending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_hybrid_set::{ContainerKind, HybridSet};
+    ///
+    /// let sparse = (0..100u32).map(|i| i * 600);
+    /// let dense = (0..40_000u32).map(|i| (1 << 16) + i * 3 / 2);
+    /// let contiguous = (2 << 16)..(2 << 16) + 50_000;
+    /// let set = HybridSet::from_iter(sparse.chain(dense).chain(contiguous));
+    /// assert_eq!(
+    ///     set.container_kinds().collect::<Vec<_>>(),
+    ///     vec![ContainerKind::Array, ContainerKind::Bitmap, ContainerKind::Runs]
+    /// );
+    /// ```
+    pub fn container_kinds(&self) -> impl Iterator<Item = ContainerKind> + '_ {
+        self.containers.iter().map(|container| container.kind())
+    }
+
+    /// The members (in ascending order).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_hybrid_set::HybridSet;
+    ///
+    /// let set = HybridSet::<u32>::from([70_000, 2, 64]);
+    /// assert_eq!(set.values().collect::<Vec<_>>(), vec![2, 64, 70_000]);
+    /// ```
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        self.keys
+            .iter()
+            .zip(self.containers.iter())
+            .flat_map(|(key, container)| container.members(0).map(|low| T::join(*key, low)))
+    }
+
+    /// An iterator over the members that can be combined with other ordered set iterators.
+    /// The members that are iterated over (as opposed to seeked past) are stored in `arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_hybrid_set::HybridSet;
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator, ValueArena};
+    ///
+    /// let hybrid = HybridSet::<u64>::from_iter(0..100_000);
+    /// let list = OrdListSet::<u64>::from([5, 50, 500, 50_000, 1 << 40]);
+    /// let btree = BTreeSet::<u64>::from([50, 5_000]);
+    /// let arena = ValueArena::default();
+    /// let iter = hybrid.iter_in(&arena).intersection(list.iter()).difference(btree.oso_iter());
+    /// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![5, 500, 50_000]);
+    /// ```
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<T>) -> HybridSetIter<'a, T> {
+        HybridSetIter {
+            set: self,
+            arena,
+            container: 0,
+            low: 0,
+            item: None,
+        }
+    }
+
+    // Combine the two sets chunk by chunk.
+    fn combine(&self, other: &Self, op: SetOp) -> Self {
+        let mut chunks = vec![];
+        let (mut i, mut j) = (0, 0);
+        loop {
+            let chunk = match (self.keys.get(i), other.keys.get(j)) {
+                (Some(l), Some(r)) if l == r => {
+                    let chunk = self.containers[i].combine(&other.containers[j], op);
+                    i += 1;
+                    j += 1;
+                    chunk.map(|container| (*l, container))
+                }
+                (Some(l), Some(r)) if l < r => {
+                    i += 1;
+                    if op.keep(true, false) {
+                        Some((*l, self.containers[i - 1].clone()))
+                    } else {
+                        None
+                    }
+                }
+                (Some(l), None) => {
+                    i += 1;
+                    if op.keep(true, false) {
+                        Some((*l, self.containers[i - 1].clone()))
+                    } else {
+                        None
+                    }
+                }
+                (_, Some(r)) => {
+                    j += 1;
+                    if op.keep(false, true) {
+                        Some((*r, other.containers[j - 1].clone()))
+                    } else {
+                        None
+                    }
+                }
+                (None, None) => break,
+            };
+            chunks.extend(chunk);
+        }
+        Self::from_chunks(chunks)
+    }
+
+    /// The members of `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, SetOp::Union)
+    }
+
+    /// The members of both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, SetOp::Intersection)
+    }
+
+    /// The members of `self` that aren't members of `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, SetOp::Difference)
+    }
+
+    /// The members of `self` or `other` but not both.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.combine(other, SetOp::SymmetricDifference)
+    }
+}
+
+impl<T: HybridInt> Default for HybridSet<T> {
+    fn default() -> Self {
+        Self::empty_set()
+    }
+}
+
+impl<T: HybridInt> PartialEq for HybridSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        // NB: containers are always in their canonical (smallest) representation
+        self.keys == other.keys && self.containers == other.containers
+    }
+}
+
+impl<T: HybridInt> Eq for HybridSet<T> {}
+
+impl<T: HybridInt + fmt::Debug> fmt::Debug for HybridSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.values()).finish()
+    }
+}
+
+impl<T: HybridInt> FromIterator<T> for HybridSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut members: Vec<T> = iter.into_iter().collect();
+        members.sort_unstable();
+        members.dedup();
+        let mut groups: Vec<(u64, Vec<u16>)> = vec![];
+        for member in members {
+            match groups.last_mut() {
+                Some((high, lows)) if *high == member.high() => lows.push(member.low()),
+                _ => groups.push((member.high(), vec![member.low()])),
+            }
+        }
+        let chunks = groups
+            .into_iter()
+            .filter_map(|(high, lows)| Some((high, Container::from_sorted(lows)?)))
+            .collect();
+        Self::from_chunks(chunks)
+    }
+}
+
+impl<T: HybridInt, const N: usize> From<[T; N]> for HybridSet<T> {
+    fn from(members: [T; N]) -> Self {
+        Self::from_iter(members)
+    }
+}
+
+impl<T: HybridInt> From<&OrdListSet<T>> for HybridSet<T> {
+    fn from(list: &OrdListSet<T>) -> Self {
+        Self::from_iter(list.iter().copied())
+    }
+}
+
+impl<T: HybridInt> From<&HybridSet<T>> for OrdListSet<T> {
+    /// Create an OrdListSet<T> from the members of a HybridSet<T>.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_hybrid_set::HybridSet;
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let list = OrdListSet::<u64>::from([1, 2, 3, 5, 7, 800, 1 << 33]);
+    /// let hybrid = HybridSet::from(&list);
+    /// assert_eq!(OrdListSet::from(&hybrid), list);
+    /// ```
+    fn from(set: &HybridSet<T>) -> Self {
+        set.values().collect()
+    }
+}
+
+impl<T: HybridInt> Sub<&HybridSet<T>> for &HybridSet<T> {
+    type Output = HybridSet<T>;
+
+    /// Returns the difference of `self` and `rhs` as a new `HybridSet<T>`.
+    fn sub(self, rhs: &HybridSet<T>) -> HybridSet<T> {
+        self.difference(rhs)
+    }
+}
+
+impl<T: HybridInt> BitAnd<&HybridSet<T>> for &HybridSet<T> {
+    type Output = HybridSet<T>;
+
+    /// Returns the intersection of `self` and `rhs` as a new `HybridSet<T>`.
+    fn bitand(self, rhs: &HybridSet<T>) -> HybridSet<T> {
+        self.intersection(rhs)
+    }
+}
+
+impl<T: HybridInt> BitXor<&HybridSet<T>> for &HybridSet<T> {
+    type Output = HybridSet<T>;
+
+    /// Returns the symmetric difference of `self` and `rhs` as a new `HybridSet<T>`.
+    fn bitxor(self, rhs: &HybridSet<T>) -> HybridSet<T> {
+        self.symmetric_difference(rhs)
+    }
+}
+
+impl<T: HybridInt> BitOr<&HybridSet<T>> for &HybridSet<T> {
+    type Output = HybridSet<T>;
+
+    /// Returns the union of `self` and `rhs` as a new `HybridSet<T>`.
+    fn bitor(self, rhs: &HybridSet<T>) -> HybridSet<T> {
+        self.union(rhs)
+    }
+}
+
+/// An iterator over the members of a `HybridSet`.  Seeks binary search the chunk keys and
+/// then the target's container.
+pub struct HybridSetIter<'a, T> {
+    set: &'a HybridSet<T>,
+    arena: &'a ValueArena<T>,
+    // The current container and the least low value that may be visited next
+    container: usize,
+    low: u32,
+    // The next member (once it has been stored in the arena)
+    item: Option<&'a T>,
+}
+
+impl<'a, T> Clone for HybridSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            arena: self.arena,
+            container: self.container,
+            low: self.low,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a, T: HybridInt> HybridSetIter<'a, T> {
+    // The next member's low value (moving on to the next container if necessary).
+    fn position(&mut self) -> Option<u16> {
+        loop {
+            let container = self.set.containers.get(self.container)?;
+            if let Some(low) = container.next_member(self.low) {
+                self.low = low as u32;
+                return Some(low);
+            }
+            self.container += 1;
+            self.low = 0;
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        match self.set.containers.get(self.container) {
+            Some(container) => {
+                let next_offset = match self.set.offsets.get(self.container + 1) {
+                    Some(offset) => *offset,
+                    None => self.set.len,
+                };
+                self.set.len - next_offset + container.count_from(self.low)
+            }
+            None => 0,
+        }
+    }
+
+    // Move to the first member at or after `low` in the chunk with key `high`.
+    fn seek_to(&mut self, high: u64, low: u32) {
+        // Make sure we don't go backwards
+        if let Some(current) = self.position() {
+            if (self.set.keys[self.container], current as u32) < (high, low) {
+                let index = self.set.keys.partition_point(|key| *key < high);
+                self.container = index;
+                self.low = if self.set.keys.get(index) == Some(&high) {
+                    low
+                } else {
+                    0
+                };
+                self.item = None;
+            }
+        }
+    }
+}
+
+impl<'a, T: HybridInt> Iterator for HybridSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        self.low += 1;
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: HybridInt> PeepAdvanceIter<'a, T> for HybridSetIter<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        if self.item.is_none() {
+            let low = self.position()?;
+            let member = T::join(self.set.keys[self.container], low);
+            self.item = Some(self.arena.alloc(member));
+        }
+        self.item
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            let remaining = self.remaining();
+            write!(
+                f,
+                "HybridSetIter[pos={}, remaining={}]",
+                self.set.len - remaining,
+                remaining
+            )
+        } else {
+            write!(f, "HybridSetIter[containers={}]", self.set.containers.len())
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Advance this iterator to the next member at or after the given item.  Implementation
+    /// binary searches for the target's chunk and then seeks within its container.
+    ///
+    /// Example
+    /// ```
+    /// use ord_hybrid_set::HybridSet;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    ///
+    /// let set = HybridSet::<u32>::from([1, 7, 8, 9, 200_000, 3]);
+    /// let arena = ValueArena::default();
+    /// let mut iter = set.iter_in(&arena);
+    /// iter.advance_until(&3);
+    /// assert_eq!(iter.next(), Some(&3));
+    /// iter.advance_until(&10);
+    /// assert_eq!(iter.peep(), Some(&200_000));
+    /// iter.advance_until(&5);
+    /// assert_eq!(iter.next(), Some(&200_000));
+    /// iter.advance_until(&300_000);
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    fn advance_until(&mut self, target: &T) {
+        self.seek_to(target.high(), target.low() as u32);
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        // NB: a low value of CHUNK_SIZE moves on to the next container
+        self.seek_to(target.high(), target.low() as u32 + 1);
+    }
+}
+
+impl<'a, T: HybridInt> OrdSetIterSetOpsIterator<'a, T> for HybridSetIter<'a, T> {}
+
+impl<'a, T: HybridInt> fmt::Debug for HybridSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T: HybridInt> fmt::Display for HybridSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use container::CHUNK_SIZE;
+
+    #[test]
+    fn set_algebra() {
+        // a mixture of sparse, dense and contiguous chunks
+        let a = HybridSet::<u32>::from_iter(
+            (0..300_000)
+                .filter(|i| i % 3 == 0 || i / CHUNK_SIZE == 2)
+                .chain((0..100).map(|i| 500_000 + i * 1000)),
+        );
+        let b = HybridSet::<u32>::from_iter(
+            (0..200_000)
+                .filter(|i| i % 5 == 0)
+                .chain(140_000..160_000)
+                .chain((0..50).map(|i| 510_000 + i * 1000)),
+        );
+        let a_list = OrdListSet::from(&a);
+        let b_list = OrdListSet::from(&b);
+        assert_eq!(OrdListSet::from(&(&a | &b)), &a_list | &b_list);
+        assert_eq!(OrdListSet::from(&(&a & &b)), &a_list & &b_list);
+        assert_eq!(OrdListSet::from(&(&a - &b)), &a_list - &b_list);
+        assert_eq!(OrdListSet::from(&(&a ^ &b)), &a_list ^ &b_list);
+        assert!((&a ^ &a).is_empty());
+        assert_eq!((&b - &a).len(), b.len() - (&a & &b).len());
+        assert_eq!(&(&a | &b) - &b, &a - &b);
+    }
+
+    #[test]
+    fn container_kinds() {
+        let set = HybridSet::<u32>::from_iter((0..CHUNK_SIZE).filter(|i| i % 2 == 0));
+        assert_eq!(
+            set.container_kinds().collect::<Vec<_>>(),
+            vec![ContainerKind::Bitmap]
+        );
+        // thinning a bitmap can turn it into an array
+        let sparse = HybridSet::<u32>::from_iter((0..CHUNK_SIZE).filter(|i| i % 64 != 0));
+        let thinned = &set - &sparse;
+        assert_eq!(
+            thinned.container_kinds().collect::<Vec<_>>(),
+            vec![ContainerKind::Array]
+        );
+        assert_eq!(thinned.len(), 1024);
+        // and filling the gaps can turn it into runs
+        let filled = &set | &HybridSet::from_iter((0..CHUNK_SIZE).filter(|i| i % 2 == 1));
+        assert_eq!(
+            filled.container_kinds().collect::<Vec<_>>(),
+            vec![ContainerKind::Runs]
+        );
+        assert_eq!(filled.len(), CHUNK_SIZE as usize);
+    }
+
+    #[test]
+    fn iteration() {
+        let members = [0u64, 65_535, 65_536, 1 << 20, (1 << 40) + 7, u64::MAX];
+        let set = HybridSet::from(members);
+        let arena = ValueArena::default();
+        assert_eq!(set.iter_in(&arena).cloned().collect::<Vec<_>>(), members);
+        assert_eq!(arena.len(), members.len());
+        assert!(set.values().eq(members));
+        let mut iter = set.iter_in(&arena);
+        assert_eq!(iter.size_hint(), (6, Some(6)));
+        iter.advance_after(&65_535);
+        assert_eq!(iter.next(), Some(&65_536));
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(format!("{iter:?}"), "HybridSetIter[pos=3, remaining=3]");
+        assert_eq!(format!("{iter}"), "HybridSetIter[containers=5]");
+        iter.advance_until(&(1 << 30));
+        assert_eq!(iter.peep(), Some(&((1 << 40) + 7)));
+        iter.advance_after(&((1 << 40) + 7));
+        assert_eq!(iter.next(), Some(&u64::MAX));
+        iter.advance_after(&u64::MAX);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert!(set.contains(&(1 << 20)) && !set.contains(&((1 << 20) + 1)));
+    }
+}
@@ -0,0 +1,511 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! A compact self describing binary encoding of `OrdListSet`s.
+//!
+//! The encoding is:
+//! - a header comprising the magic bytes `OLS\0`, a format version byte, an element type tag
+//!   byte and the number of members (as a little endian `u64`),
+//! - the members in ascending order: integers as the varint encoded gap from their
+//!   predecessor (the first as its offset from the type's minimum) and byte strings as a
+//!   varint length followed by the bytes, and
+//! - a CRC-32 of all of the above (as a little endian `u32`).
+//!
+//! Example:
+//! ```
+//! use ord_list_set::OrdListSet;
+//!
+//! let set: OrdListSet<u64> = (0..1000).map(|i| 1_000_000 + i * 7).collect();
+//! let mut bytes = vec![];
+//! set.write_to(&mut bytes).unwrap();
+//! assert!(bytes.len() < 1100);
+//! assert_eq!(OrdListSet::<u64>::read_from(&bytes[..]).unwrap(), set);
+//! assert!(OrdListSet::<u32>::read_from(&bytes[..]).is_err());
+//! ```
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{self, Read, Write};
+
+use super::*;
+
+const MAGIC: [u8; 4] = *b"OLS\0";
+const VERSION: u8 = 1;
+// Don't trust the member count in the header for more than this many members.
+const MAX_PREALLOCATION: usize = 4096;
+
+/// The errors that can occur when reading an `OrdListSet` from its binary encoding.
+#[derive(Debug)]
+pub enum BinaryFormatError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The input ended before the encoding was complete.
+    Truncated,
+    /// The input doesn't start with the magic bytes.
+    BadMagic,
+    /// The encoding uses a format version that this version of the crate doesn't know.
+    UnsupportedVersion(u8),
+    /// The encoded elements aren't of the type being read.
+    TypeMismatch { expected: u8, found: u8 },
+    /// The input is malformed (e.g. an overlong varint or an out of range value).
+    Corrupt(&'static str),
+    /// The checksum of the input doesn't match the one recorded in it.
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The members aren't sorted or contain duplicates.
+    Ordering(OrdListSetError),
+}
+
+impl fmt::Display for BinaryFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BinaryFormatError::*;
+        match self {
+            Io(error) => write!(f, "I/O error: {}", error),
+            Truncated => write!(f, "input is truncated"),
+            BadMagic => write!(f, "input is not an encoded OrdListSet"),
+            UnsupportedVersion(version) => write!(f, "unsupported format version: {}", version),
+            TypeMismatch { expected, found } => write!(
+                f,
+                "element type mismatch: expected tag {} found tag {}",
+                expected, found
+            ),
+            Corrupt(what) => write!(f, "input is corrupt: {}", what),
+            ChecksumMismatch { expected, found } => write!(
+                f,
+                "checksum mismatch: expected {:#010x} found {:#010x}",
+                expected, found
+            ),
+            Ordering(error) => write!(f, "invalid members: {}", error),
+        }
+    }
+}
+
+impl Error for BinaryFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BinaryFormatError::Io(error) => Some(error),
+            BinaryFormatError::Ordering(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BinaryFormatError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            BinaryFormatError::Truncated
+        } else {
+            BinaryFormatError::Io(error)
+        }
+    }
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+// The running CRC-32 (IEEE) of the bytes passing through a reader or writer.
+struct Checksummed<S> {
+    stream: S,
+    crc: u32,
+}
+
+impl<S> Checksummed<S> {
+    fn new(stream: S) -> Self {
+        Self { stream, crc: !0 }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.crc = CRC32_TABLE[((self.crc ^ *byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn checksum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.stream.write(buf)?;
+        self.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stream.read(buf)?;
+        self.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+    while value >= 0x80 {
+        bytes[len] = (value as u8) | 0x80;
+        value >>= 7;
+        len += 1;
+    }
+    bytes[len] = value as u8;
+    writer.write_all(&bytes[..=len])
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64, BinaryFormatError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        let bits = (byte[0] & 0x7f) as u64;
+        if shift == 63 && bits > 1 {
+            break;
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(BinaryFormatError::Corrupt("overlong varint"))
+}
+
+/// The types whose `OrdListSet`s have a binary encoding.
+pub trait BinaryElement: Ord + Sized {
+    /// Identifies the element type in the header.
+    const TYPE_TAG: u8;
+
+    /// Write this element given its predecessor (if any).
+    fn write_element(&self, previous: Option<&Self>, writer: &mut impl Write) -> io::Result<()>;
+
+    /// Read an element given its predecessor (if any).  `OrdListSet::read_from()` checks that
+    /// the element is greater than `previous`.
+    fn read_element(
+        previous: Option<&Self>,
+        reader: &mut impl Read,
+    ) -> Result<Self, BinaryFormatError>;
+}
+
+// Integers are mapped (order preservingly) to u64 and stored as the gap from their
+// predecessor (or from the mapping of the type's minimum).
+macro_rules! impl_binary_element_for_integer {
+    ($($int:ty as $uint:ty => ($tag:expr, $bias:expr)),+) => {
+        $(
+            impl BinaryElement for $int {
+                const TYPE_TAG: u8 = $tag;
+
+                fn write_element(&self, previous: Option<&Self>, writer: &mut impl Write) -> io::Result<()> {
+                    let base = previous.map_or(0, |previous| (*previous as $uint as u64) ^ $bias);
+                    write_varint(writer, ((*self as $uint as u64) ^ $bias) - base)
+                }
+
+                fn read_element(
+                    previous: Option<&Self>,
+                    reader: &mut impl Read,
+                ) -> Result<Self, BinaryFormatError> {
+                    let gap = read_varint(reader)?;
+                    let value = match previous {
+                        // NB: the caller supplies the index
+                        Some(_) if gap == 0 => return Err(BinaryFormatError::Ordering(OrdListSetError::Duplicate { index: 0 })),
+                        Some(previous) => ((*previous as $uint as u64) ^ $bias).checked_add(gap),
+                        None => Some(gap),
+                    };
+                    match value {
+                        Some(value) if value <= <$uint>::MAX as u64 => Ok(((value ^ $bias) as $uint) as $int),
+                        _ => Err(BinaryFormatError::Corrupt("integer out of range")),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_binary_element_for_integer!(
+    u8 as u8 => (1, 0),
+    u16 as u16 => (2, 0),
+    u32 as u32 => (3, 0),
+    u64 as u64 => (4, 0),
+    i8 as u8 => (5, 0x80),
+    i16 as u16 => (6, 0x8000),
+    i32 as u32 => (7, 0x8000_0000),
+    i64 as u64 => (8, 0x8000_0000_0000_0000)
+);
+
+// Pointer sized integers are encoded as their 64 bit equivalents (with the same tag) so that
+// files are portable between targets of different pointer widths.
+macro_rules! impl_binary_element_by_widening {
+    ($($int:ty => $wide:ty),+) => {
+        $(
+            impl BinaryElement for $int {
+                const TYPE_TAG: u8 = <$wide>::TYPE_TAG;
+
+                fn write_element(&self, previous: Option<&Self>, writer: &mut impl Write) -> io::Result<()> {
+                    let previous = previous.map(|previous| *previous as $wide);
+                    (*self as $wide).write_element(previous.as_ref(), writer)
+                }
+
+                fn read_element(
+                    previous: Option<&Self>,
+                    reader: &mut impl Read,
+                ) -> Result<Self, BinaryFormatError> {
+                    let previous = previous.map(|previous| *previous as $wide);
+                    let value = <$wide>::read_element(previous.as_ref(), reader)?;
+                    <$int>::try_from(value).map_err(|_| BinaryFormatError::Corrupt("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+impl_binary_element_by_widening!(usize => u64, isize => i64);
+
+fn write_bytes(bytes: &[u8], writer: &mut impl Write) -> io::Result<()> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> Result<Vec<u8>, BinaryFormatError> {
+    let len = read_varint(reader)?;
+    let mut bytes = vec![];
+    // NB: a corrupt length can't cause a huge allocation
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        Err(BinaryFormatError::Truncated)
+    } else {
+        Ok(bytes)
+    }
+}
+
+impl BinaryElement for Vec<u8> {
+    const TYPE_TAG: u8 = 16;
+
+    fn write_element(&self, _previous: Option<&Self>, writer: &mut impl Write) -> io::Result<()> {
+        write_bytes(self, writer)
+    }
+
+    fn read_element(
+        _previous: Option<&Self>,
+        reader: &mut impl Read,
+    ) -> Result<Self, BinaryFormatError> {
+        read_bytes(reader)
+    }
+}
+
+impl BinaryElement for String {
+    const TYPE_TAG: u8 = 17;
+
+    fn write_element(&self, _previous: Option<&Self>, writer: &mut impl Write) -> io::Result<()> {
+        write_bytes(self.as_bytes(), writer)
+    }
+
+    fn read_element(
+        _previous: Option<&Self>,
+        reader: &mut impl Read,
+    ) -> Result<Self, BinaryFormatError> {
+        String::from_utf8(read_bytes(reader)?)
+            .map_err(|_| BinaryFormatError::Corrupt("string is not valid UTF-8"))
+    }
+}
+
+impl<T: Ord + BinaryElement> OrdListSet<T> {
+    /// Write the binary encoding of this set to `writer`.  As many small writes are made,
+    /// `writer` should be buffered.
+    pub fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        let mut writer = Checksummed::new(writer);
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION, T::TYPE_TAG])?;
+        writer.write_all(&(self.members.len() as u64).to_le_bytes())?;
+        let mut previous = None;
+        for member in self.members.iter() {
+            member.write_element(previous, &mut writer)?;
+            previous = Some(member);
+        }
+        let checksum = writer.checksum();
+        writer.stream.write_all(&checksum.to_le_bytes())?;
+        writer.flush()
+    }
+
+    /// Read a set from its binary encoding in `reader`.  As many small reads are made,
+    /// `reader` should be buffered.
+    pub fn read_from(reader: impl Read) -> Result<Self, BinaryFormatError> {
+        let mut reader = Checksummed::new(reader);
+        let mut header = [0u8; 14];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(BinaryFormatError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(BinaryFormatError::UnsupportedVersion(header[4]));
+        }
+        if header[5] != T::TYPE_TAG {
+            return Err(BinaryFormatError::TypeMismatch {
+                expected: T::TYPE_TAG,
+                found: header[5],
+            });
+        }
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&header[6..]);
+        let count = u64::from_le_bytes(count);
+        let mut members: Vec<T> = Vec::with_capacity((count as usize).min(MAX_PREALLOCATION));
+        for _ in 0..count {
+            let index = members.len();
+            let member = match T::read_element(members.last(), &mut reader) {
+                Err(BinaryFormatError::Ordering(_)) => {
+                    return Err(BinaryFormatError::Ordering(OrdListSetError::Duplicate {
+                        index,
+                    }))
+                }
+                result => result?,
+            };
+            match members.last().map(|last| member.cmp(last)) {
+                Some(Ordering::Equal) => {
+                    return Err(BinaryFormatError::Ordering(OrdListSetError::Duplicate {
+                        index,
+                    }))
+                }
+                Some(Ordering::Less) => {
+                    return Err(BinaryFormatError::Ordering(OrdListSetError::OutOfOrder {
+                        index,
+                    }))
+                }
+                _ => members.push(member),
+            }
+        }
+        let expected = reader.checksum();
+        let mut found = [0u8; 4];
+        reader.stream.read_exact(&mut found)?;
+        let found = u32::from_le_bytes(found);
+        if found != expected {
+            return Err(BinaryFormatError::ChecksumMismatch { expected, found });
+        }
+        Ok(Self {
+            members: members.into_boxed_slice(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<T: BinaryElement>(set: &OrdListSet<T>) -> Vec<u8> {
+        let mut bytes = vec![];
+        set.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let set: OrdListSet<i32> = [i32::MIN, -5, 0, 7, i32::MAX].iter().copied().collect();
+        assert_eq!(OrdListSet::read_from(&encode(&set)[..]).unwrap(), set);
+        let set: OrdListSet<u64> = [0, 1, u64::MAX].iter().copied().collect();
+        assert_eq!(OrdListSet::read_from(&encode(&set)[..]).unwrap(), set);
+        let set: OrdListSet<String> = ["", "a", "ab", "b\u{e9}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(OrdListSet::read_from(&encode(&set)[..]).unwrap(), set);
+        // pointer sized integers are interchangeable with their 64 bit equivalents
+        let set: OrdListSet<isize> = [isize::MIN, -1, 0, 3, isize::MAX].iter().copied().collect();
+        let wide: OrdListSet<i64> = set.iter().map(|value| *value as i64).collect();
+        assert_eq!(encode(&set), encode(&wide));
+        assert_eq!(OrdListSet::read_from(&encode(&set)[..]).unwrap(), set);
+        let set: OrdListSet<usize> = [0, 5, usize::MAX].iter().copied().collect();
+        let wide: OrdListSet<u64> = set.iter().map(|value| *value as u64).collect();
+        assert_eq!(
+            OrdListSet::<u64>::read_from(&encode(&set)[..]).unwrap(),
+            wide
+        );
+        let set = OrdListSet::<Vec<u8>>::empty_set();
+        assert_eq!(encode(&set).len(), 18);
+        assert_eq!(OrdListSet::read_from(&encode(&set)[..]).unwrap(), set);
+        // CRC-32 check value
+        let mut checksummed = Checksummed::new(io::sink());
+        checksummed.write_all(b"123456789").unwrap();
+        assert_eq!(checksummed.checksum(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn errors() {
+        let set = OrdListSet::<u16>::from([1, 300, 60_000]);
+        let bytes = encode(&set);
+        for len in 0..bytes.len() {
+            assert!(matches!(
+                OrdListSet::<u16>::read_from(&bytes[..len]),
+                Err(BinaryFormatError::Truncated)
+            ));
+        }
+        let mut corrupt = bytes.clone();
+        corrupt[15] ^= 1;
+        assert!(matches!(
+            OrdListSet::<u16>::read_from(&corrupt[..]),
+            Err(BinaryFormatError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            OrdListSet::<u8>::read_from(&bytes[..]),
+            Err(BinaryFormatError::TypeMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+        let mut corrupt = bytes.clone();
+        corrupt[4] = 9;
+        assert!(matches!(
+            OrdListSet::<u16>::read_from(&corrupt[..]),
+            Err(BinaryFormatError::UnsupportedVersion(9))
+        ));
+        assert!(matches!(
+            OrdListSet::<u16>::read_from(&b"not a set at all"[..]),
+            Err(BinaryFormatError::BadMagic)
+        ));
+        // a zero gap is a duplicate
+        let mut writer = Checksummed::new(vec![]);
+        writer.write_all(&MAGIC).unwrap();
+        writer.write_all(&[VERSION, u16::TYPE_TAG]).unwrap();
+        writer.write_all(&3u64.to_le_bytes()).unwrap();
+        writer.write_all(&[1, 2, 0]).unwrap();
+        let checksum = writer.checksum();
+        let mut bytes = writer.stream;
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        let error = OrdListSet::<u16>::read_from(&bytes[..]).unwrap_err();
+        assert!(matches!(
+            error,
+            BinaryFormatError::Ordering(OrdListSetError::Duplicate { index: 2 })
+        ));
+        assert_eq!(
+            error.to_string(),
+            "invalid members: member 2 is a duplicate of its predecessor"
+        );
+        assert!(error.source().is_some());
+        // strings out of order
+        let strings: OrdListSet<String> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+        let mut bytes = encode(&strings);
+        bytes.swap(15, 17);
+        assert!(matches!(
+            OrdListSet::<String>::read_from(&bytes[..]),
+            Err(BinaryFormatError::Ordering(OrdListSetError::OutOfOrder {
+                index: 1
+            }))
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets that borrow their (already sorted) members, e.g. from a memory mapped file or the
+//! output of `include_bytes!()`, rather than owning them.
+
+use std::{mem, slice};
+
+use super::*;
+
+/// Plain old data types that can be viewed in place in a slice of bytes (in native byte
+/// order).
+///
+/// # Safety
+///
+/// Every bit pattern of the type's size must be a valid value of the type.
+pub unsafe trait PlainOldData: Ord + Copy + 'static {}
+
+macro_rules! impl_plain_old_data {
+    ($($int:ty),+) => {
+        $(
+            unsafe impl PlainOldData for $int {}
+        )+
+    };
+}
+
+impl_plain_old_data!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// An immutable view of a sorted slice (with no duplicates) as a set.  The slice is
+/// validated once (when the view is created) and then offers the query API of `OrdListSet`
+/// without copying the members.
+///
+/// # Examples
+///
+/// ```
+/// use ord_list_set::{borrowed::OrdListSetRef, OrdListSet};
+///
+/// let ids = [2u64, 3, 5, 7, 11, 13];
+/// let primes = OrdListSetRef::new(&ids).unwrap();
+/// assert!(primes.contains(&11) && !primes.contains(&9));
+/// assert_eq!(primes.item_items(4..12), [5, 7, 11]);
+/// let odds = OrdListSet::<u64>::from([1, 3, 5, 7, 9]);
+/// let both: Vec<u64> = primes.intersection(&odds.view()).cloned().collect();
+/// assert_eq!(both, [3, 5, 7]);
+/// assert!(OrdListSetRef::new(&[1, 3, 2]).is_err());
+/// ```
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OrdListSetRef<'a, T: Ord> {
+    pub(crate) members: &'a [T],
+}
+
+impl<'a, T: Ord> Clone for OrdListSetRef<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Ord> Copy for OrdListSetRef<'a, T> {}
+
+impl<'a, T: Ord> Default for OrdListSetRef<'a, T> {
+    fn default() -> Self {
+        Self { members: &[] }
+    }
+}
+
+impl<'a, T: Ord> OrdListSetRef<'a, T> {
+    /// View `members` as a set if they are sorted with no duplicates.
+    pub fn new(members: &'a [T]) -> Result<Self, OrdListSetError> {
+        check_sorted_and_no_dups(members)?;
+        Ok(Self { members })
+    }
+
+    /// View `members` as a set without checking that they're sorted with no duplicates.
+    ///
+    /// # Safety
+    ///
+    /// `members` must be sorted with no duplicates.  (Set operations and searches will give
+    /// nonsensical results if they aren't).
+    pub unsafe fn new_unchecked(members: &'a [T]) -> Self {
+        debug_assert!(is_sorted_and_no_dups(members));
+        Self { members }
+    }
+
+    /// Return number of members in this set.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Return `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// The (borrowed) members.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.members
+    }
+
+    /// Return an iterator over the members in ascending order.
+    pub fn iter(&self) -> OrdListSetIter<'a, T> {
+        OrdListSetIter {
+            elements: self.members,
+            index: 0,
+        }
+    }
+
+    ///Returns true if the set contains an element equal to the value.
+    pub fn contains(&self, item: &T) -> bool {
+        self.members.binary_search(item).is_ok()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        self.members.get(index)
+    }
+
+    /// Returns a reference to a subslice of the set's elements using indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::borrowed::OrdListSetRef;
+    ///
+    /// let set = OrdListSetRef::new(&["a", "d", "f", "h", "j", "k", "l"]).unwrap();
+    ///
+    /// assert!(set.items(set.len()..).is_empty());
+    /// assert_eq!(set.items(..=2), ["a", "d", "f",]);
+    /// assert_eq!(set.items(1..5), ["d", "f", "h", "j"]);
+    /// assert!(set.items(5..20).is_empty());
+    /// ```
+    pub fn items(&self, range: impl RangeBounds<usize>) -> &'a [T] {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.members.get(bounds).unwrap_or(&[])
+    }
+
+    /// Returns a reference to a subslice of the set's elements using items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::borrowed::OrdListSetRef;
+    ///
+    /// let set = OrdListSetRef::new(&["a", "d", "f", "h", "j", "k", "l"]).unwrap();
+    ///
+    /// assert!(set.item_items("m"..).is_empty());
+    /// assert_eq!(set.item_items(..="g"), ["a", "d", "f",]);
+    /// assert_eq!(set.item_items("c".."k"), ["d", "f", "h", "j"]);
+    /// assert_eq!(set.item_items("d"..="k"), ["d", "f", "h", "j", "k"]);
+    /// ```
+    pub fn item_items(&self, range: impl RangeBounds<T>) -> &'a [T] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.members.partition_point(|member| member < start),
+            Bound::Excluded(start) => self.members.partition_point(|member| member <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.members.partition_point(|member| member <= end),
+            Bound::Excluded(end) => self.members.partition_point(|member| member < end),
+            Bound::Unbounded => self.members.len(),
+        };
+        self.members.get(start..end).unwrap_or(&[])
+    }
+
+    /// Returns a reference to the first element in the set, if any.
+    pub fn first(&self) -> Option<&'a T> {
+        self.members.first()
+    }
+
+    /// Returns a reference to the last element in the set, if any.
+    pub fn last(&self) -> Option<&'a T> {
+        self.members.last()
+    }
+}
+
+impl<'a, T: PlainOldData> OrdListSetRef<'a, T> {
+    /// View `bytes` (members in native byte order) as a set if they are correctly aligned
+    /// and sized for `T` and the members are sorted with no duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::borrowed::OrdListSetRef;
+    ///
+    /// // e.g. `Aligned(*include_bytes!("ids.bin"))`
+    /// #[repr(align(4))]
+    /// struct Aligned([u8; 12]);
+    ///
+    /// let mut bytes = [0u8; 12];
+    /// for (chunk, id) in bytes.chunks_mut(4).zip([3u32, 30, 300]) {
+    ///     chunk.copy_from_slice(&id.to_ne_bytes());
+    /// }
+    /// let aligned = Aligned(bytes);
+    /// let set = OrdListSetRef::<u32>::from_bytes(&aligned.0).unwrap();
+    /// assert_eq!(set.items(..), [3, 30, 300]);
+    /// assert!(OrdListSetRef::<u32>::from_bytes(&aligned.0[..10]).is_err());
+    /// ```
+    #[allow(clippy::manual_is_multiple_of)] // NB: `is_multiple_of()` needs Rust 1.87
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, OrdListSetError> {
+        if bytes.len() % mem::size_of::<T>() != 0 {
+            return Err(OrdListSetError::BadLength);
+        }
+        if bytes.as_ptr() as usize % mem::align_of::<T>() != 0 {
+            return Err(OrdListSetError::Misaligned);
+        }
+        // SAFETY: the bytes are aligned and sized for `T` and `PlainOldData` guarantees that
+        // any bytes are a valid `T`
+        let members = unsafe {
+            slice::from_raw_parts(
+                bytes.as_ptr() as *const T,
+                bytes.len() / mem::size_of::<T>(),
+            )
+        };
+        Self::new(members)
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdListSetRef<'a, T> {
+    /// Visits the values in `self` but not in `other` in ascending order.
+    pub fn difference(&self, other: &Self) -> Difference<'a, T> {
+        Difference {
+            left_iter: self.iter(),
+            right_iter: other.iter(),
+        }
+    }
+
+    /// Visits the values in both `self` and `other` in ascending order.
+    pub fn intersection(&self, other: &Self) -> Intersection<'a, T> {
+        Intersection {
+            left_iter: self.iter(),
+            right_iter: other.iter(),
+        }
+    }
+
+    /// Visits the values in `self` or `other` but not in both in ascending order.
+    pub fn symmetric_difference(&self, other: &Self) -> SymmetricDifference<'a, T> {
+        SymmetricDifference {
+            left_iter: self.iter(),
+            right_iter: other.iter(),
+        }
+    }
+
+    /// Visits the values in `self` or `other` in ascending order.
+    pub fn union(&self, other: &Self) -> Union<'a, T> {
+        Union {
+            left_iter: self.iter(),
+            right_iter: other.iter(),
+        }
+    }
+
+    /// Is `other` disjoint from this set?
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.iter().is_disjoint(other.iter())
+    }
+
+    /// Is this set a proper subset of `other`?
+    pub fn is_proper_subset(&self, other: &Self) -> bool {
+        self.iter().is_proper_subset(other.iter())
+    }
+
+    /// Is this set a proper superset of `other`?
+    pub fn is_proper_superset(&self, other: &Self) -> bool {
+        self.iter().is_proper_superset(other.iter())
+    }
+
+    /// Is this set a subset of `other`?
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.iter().is_subset(other.iter())
+    }
+
+    /// Is this set a superset of `other`?
+    pub fn is_superset(&self, other: &Self) -> bool {
+        self.iter().is_superset(other.iter())
+    }
+}
+
+impl<T: Ord> OrdListSet<T> {
+    /// A (`Copy`) view of this set.
+    pub fn view(&self) -> OrdListSetRef<'_, T> {
+        OrdListSetRef {
+            members: &self.members,
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone> From<OrdListSetRef<'a, T>> for OrdListSet<T> {
+    /// Create an OrdListSet<T> by copying the members of an OrdListSetRef<T>.
+    fn from(set: OrdListSetRef<'a, T>) -> Self {
+        Self {
+            members: set.members.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        assert_eq!(
+            OrdListSetRef::new(&[1, 2, 2, 3]),
+            Err(OrdListSetError::Duplicate { index: 2 })
+        );
+        assert_eq!(
+            OrdListSetRef::new(&[1, 2, 3, 0]),
+            Err(OrdListSetError::OutOfOrder { index: 3 })
+        );
+        assert!(OrdListSetRef::<u8>::new(&[]).unwrap().is_empty());
+        let words = [1u64, 5, 9];
+        // SAFETY: only used to produce misaligned bytes
+        let bytes = unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, 3 * 8) };
+        assert_eq!(
+            OrdListSetRef::<u64>::from_bytes(bytes).unwrap().items(..),
+            words
+        );
+        assert_eq!(
+            OrdListSetRef::<u64>::from_bytes(&bytes[1..9]),
+            Err(OrdListSetError::Misaligned)
+        );
+        assert_eq!(
+            OrdListSetRef::<u64>::from_bytes(&bytes[..12]),
+            Err(OrdListSetError::BadLength)
+        );
+    }
+
+    #[test]
+    fn queries_match_owned_set() {
+        let owned = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 3));
+        let other = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 5));
+        let (view, other_view) = (owned.view(), other.view());
+        assert_eq!(view.item_items(10..=30), owned.item_items(10..=30));
+        assert_eq!(view.items(5..), owned.items(5..));
+        assert!(view.union(&other_view).eq(owned.union(&other)));
+        assert!(view.difference(&other_view).eq(owned.difference(&other)));
+        assert_eq!(OrdListSet::from(view), owned);
+        assert!(view.intersection(&other_view).all(|item| item % 15 == 0));
+        assert!(view.is_superset(&OrdListSet::from([3, 6]).view()));
+    }
+}
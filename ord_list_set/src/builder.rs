@@ -0,0 +1,214 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Batched modification of (immutable) `OrdListSet`s.
+//!
+//! A batch of insertions and removals is sorted once and then merged with the existing
+//! members in a single pass to produce the new set.  Where a batch contains more than one
+//! change for the same item the last of them wins.
+
+use super::*;
+
+/// A change to be made to the members of a set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<T> {
+    Insert(T),
+    Remove(T),
+}
+
+impl<T> Change<T> {
+    /// The item being inserted or removed.
+    pub fn item(&self) -> &T {
+        match self {
+            Change::Insert(item) | Change::Remove(item) => item,
+        }
+    }
+}
+
+// Merge the sorted `members` with `changes` (in a single pass after sorting the changes).
+fn merge<T: Ord>(
+    members: impl ExactSizeIterator<Item = T>,
+    changes: impl IntoIterator<Item = Change<T>>,
+) -> OrdListSet<T> {
+    let mut changes: Vec<Change<T>> = changes.into_iter().collect();
+    // NB: the sort is stable so that the order of changes to the same item is kept
+    changes.sort_by(|a, b| a.item().cmp(b.item()));
+    let mut result = Vec::with_capacity(members.len() + changes.len());
+    let mut members = members.peekable();
+    let mut changes = changes.into_iter().peekable();
+    while let Some(change) = changes.next() {
+        if changes
+            .peek()
+            .is_some_and(|next| next.item() == change.item())
+        {
+            // superseded
+            continue;
+        }
+        while let Some(member) = members.next_if(|member| member < change.item()) {
+            result.push(member);
+        }
+        members.next_if(|member| member == change.item());
+        if let Change::Insert(item) = change {
+            result.push(item);
+        }
+    }
+    result.extend(members);
+    OrdListSet {
+        members: result.into_boxed_slice(),
+    }
+}
+
+impl<T: Ord + Clone> OrdListSet<T> {
+    /// Return a copy of this set with `changes` applied.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_list_set::builder::Change;
+    ///
+    /// let set = OrdListSet::<u32>::from([1, 2, 3]);
+    /// let changes = vec![Change::Insert(5), Change::Remove(2), Change::Insert(2), Change::Remove(1)];
+    /// assert_eq!(set.apply(changes), OrdListSet::from([2, 3, 5]));
+    /// ```
+    pub fn apply(&self, changes: impl IntoIterator<Item = Change<T>>) -> Self {
+        merge(self.members.iter().cloned(), changes)
+    }
+
+    /// Return a copy of this set with `items` added.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<&str>::from(["b", "d"]);
+    /// assert_eq!(set.with_inserted(["e", "a", "b"]), OrdListSet::from(["a", "b", "d", "e"]));
+    /// ```
+    pub fn with_inserted(&self, items: impl IntoIterator<Item = T>) -> Self {
+        self.apply(items.into_iter().map(Change::Insert))
+    }
+
+    /// Return a copy of this set with `items` removed.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<&str>::from(["a", "b", "c", "d"]);
+    /// assert_eq!(set.with_removed(["d", "x", "b"]), OrdListSet::from(["a", "c"]));
+    /// ```
+    pub fn with_removed(&self, items: impl IntoIterator<Item = T>) -> Self {
+        self.apply(items.into_iter().map(Change::Remove))
+    }
+}
+
+/// Accumulates insertions and removals (starting from an empty or existing set) and builds
+/// the resulting set in one pass.
+///
+/// Example:
+/// ```
+/// use ord_list_set::OrdListSet;
+/// use ord_list_set::builder::OrdListSetBuilder;
+///
+/// let mut builder = OrdListSetBuilder::from(OrdListSet::<u32>::from([10, 20, 30]));
+/// builder.insert(25).insert(5).remove(10);
+/// builder.extend([40, 35]);
+/// assert_eq!(builder.build(), OrdListSet::from([5, 20, 25, 30, 35, 40]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrdListSetBuilder<T: Ord> {
+    base: OrdListSet<T>,
+    changes: Vec<Change<T>>,
+}
+
+impl<T: Ord> Default for OrdListSetBuilder<T> {
+    fn default() -> Self {
+        Self {
+            base: OrdListSet::default(),
+            changes: vec![],
+        }
+    }
+}
+
+impl<T: Ord> From<OrdListSet<T>> for OrdListSetBuilder<T> {
+    fn from(base: OrdListSet<T>) -> Self {
+        Self {
+            base,
+            changes: vec![],
+        }
+    }
+}
+
+impl<T: Ord> OrdListSetBuilder<T> {
+    /// A builder starting from an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `item` to the set being built.
+    pub fn insert(&mut self, item: T) -> &mut Self {
+        self.changes.push(Change::Insert(item));
+        self
+    }
+
+    /// Remove `item` from the set being built.
+    pub fn remove(&mut self, item: T) -> &mut Self {
+        self.changes.push(Change::Remove(item));
+        self
+    }
+
+    /// Record `change` to the set being built.
+    pub fn push(&mut self, change: Change<T>) -> &mut Self {
+        self.changes.push(change);
+        self
+    }
+
+    /// Return the number of changes recorded so far.
+    pub fn pending(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Build the set (moving rather than cloning the starting set's members).
+    pub fn build(self) -> OrdListSet<T> {
+        merge(self.base.members.into_vec().into_iter(), self.changes)
+    }
+}
+
+impl<T: Ord> Extend<T> for OrdListSetBuilder<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        self.changes.extend(items.into_iter().map(Change::Insert));
+    }
+}
+
+impl<T: Ord> Extend<Change<T>> for OrdListSetBuilder<T> {
+    fn extend<I: IntoIterator<Item = Change<T>>>(&mut self, changes: I) {
+        self.changes.extend(changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_match_btree_set() {
+        let base = OrdListSet::<u32>::from_iter((0..200).map(|i| i * 3));
+        let mut expected: BTreeSet<u32> = base.iter().cloned().collect();
+        let mut changes = vec![];
+        for i in 0..300u32 {
+            let item = (i * 37) % 650;
+            if i % 3 == 0 {
+                expected.remove(&item);
+                changes.push(Change::Remove(item));
+            } else {
+                expected.insert(item);
+                changes.push(Change::Insert(item));
+            }
+        }
+        let result = base.apply(changes.clone());
+        assert!(result.is_valid());
+        assert_eq!(result, expected);
+        let mut builder = OrdListSetBuilder::from(base);
+        builder.extend(changes);
+        assert_eq!(builder.pending(), 300);
+        assert_eq!(builder.build(), expected);
+        assert!(OrdListSetBuilder::<u32>::new().build().is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets of unsigned integers stored as blocks of delta encoded varints (with a skip table of
+//! the first member of each block) for when small gaps make 8 bytes per member wasteful.
+
+use super::*;
+
+use ord_set_iter_set_ops::ValueArena;
+
+// The number of members in each block (except possibly the last).
+const BLOCK_LEN: usize = 128;
+
+/// The unsigned integer types that can be members of a `CompressedOrdListSet`.
+pub trait Compressible: Ord + Copy + 'static {
+    fn to_u64(self) -> u64;
+
+    fn from_u64(value: u64) -> Self;
+}
+
+macro_rules! impl_compressible {
+    ($($int:ty),+) => {
+        $(
+            impl Compressible for $int {
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+
+                fn from_u64(value: u64) -> Self {
+                    value as $int
+                }
+            }
+        )+
+    };
+}
+
+impl_compressible!(u8, u16, u32, u64, usize);
+
+fn encode_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Decode the varint at the start of `bytes` returning it and the number of bytes used (or
+// `None` if it's truncated or too long for a `u64`).
+fn decode_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0;
+    for (index, byte) in bytes.iter().enumerate().take(10) {
+        let bits = (byte & 0x7f) as u64;
+        if index == 9 && bits > 1 {
+            return None;
+        }
+        value |= bits << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+/// An immutable set of unsigned integers ordered (like an `OrdListSet`) but stored as blocks
+/// of `BLOCK_LEN` members.  The first member of each block is kept in a skip table and the
+/// rest as varint encoded gaps from their predecessors so that sets with small gaps take one
+/// or two bytes per member.  Iterators decode one block at a time (as they reach it).
+///
+/// # Examples
+///
+/// ```
+/// use ord_list_set::{compressed::CompressedOrdListSet, OrdListSet};
+///
+/// let list = (0..10_000).map(|i| 1_000_000 + i * 3).collect::<OrdListSet<u64>>();
+/// let compressed = CompressedOrdListSet::from(&list);
+/// assert_eq!(compressed.len(), 10_000);
+/// assert!(compressed.encoded_size() < list.len() * 2);
+/// assert!(compressed.contains(&1_000_300) && !compressed.contains(&1_000_301));
+/// assert_eq!(OrdListSet::from(&compressed), list);
+/// ```
+pub struct CompressedOrdListSet<T> {
+    // The first member of each block and where the block's gaps start in `bytes`
+    firsts: Box<[T]>,
+    offsets: Box<[usize]>,
+    bytes: Box<[u8]>,
+    len: usize,
+}
+
+impl<T: Compressible> CompressedOrdListSet<T> {
+    pub fn empty_set() -> Self {
+        Self::from_sorted(std::iter::empty())
+    }
+
+    // NB: `members` must be strictly increasing
+    fn from_sorted(members: impl IntoIterator<Item = T>) -> Self {
+        let mut firsts = vec![];
+        let mut offsets = vec![];
+        let mut bytes = vec![];
+        let mut len = 0;
+        let mut previous = 0;
+        for member in members {
+            let value = member.to_u64();
+            if len % BLOCK_LEN == 0 {
+                firsts.push(member);
+                offsets.push(bytes.len());
+            } else {
+                debug_assert!(value > previous);
+                encode_varint(&mut bytes, value - previous);
+            }
+            previous = value;
+            len += 1;
+        }
+        Self {
+            firsts: firsts.into_boxed_slice(),
+            offsets: offsets.into_boxed_slice(),
+            bytes: bytes.into_boxed_slice(),
+            len,
+        }
+    }
+
+    /// Return number of members in this set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of bytes used to store the members (including the skip table).
+    pub fn encoded_size(&self) -> usize {
+        self.bytes.len()
+            + self.firsts.len() * (std::mem::size_of::<T>() + std::mem::size_of::<usize>())
+    }
+
+    fn block_len(&self, block: usize) -> usize {
+        BLOCK_LEN.min(self.len - block * BLOCK_LEN)
+    }
+
+    // The members of the given block.
+    fn decode(&self, block: usize) -> impl Iterator<Item = T> + '_ {
+        let mut value = self.firsts[block].to_u64();
+        let mut offset = self.offsets[block];
+        // NB: the gaps are only undecodable if the encoding has been corrupted
+        (0..self.block_len(block)).map_while(move |index| {
+            if index > 0 {
+                let (gap, used) = decode_varint(&self.bytes[offset..])?;
+                value = value.checked_add(gap)?;
+                offset += used;
+            }
+            Some(T::from_u64(value))
+        })
+    }
+
+    // The index of the block that would contain `item` (if it's a member).
+    fn block_for(&self, item: &T) -> usize {
+        self.firsts
+            .partition_point(|first| first <= item)
+            .saturating_sub(1)
+    }
+
+    /// Returns `true` if the set contains the specified item.  Only the block that may contain
+    /// `item` is decoded.
+    pub fn contains(&self, item: &T) -> bool {
+        !self.is_empty()
+            && self
+                .decode(self.block_for(item))
+                .any(|member| member == *item)
+    }
+
+    /// Return the members in ascending order (decoding a block at a time).
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::compressed::CompressedOrdListSet;
+    ///
+    /// let compressed = [300u32, 2, 64].iter().copied().collect::<CompressedOrdListSet<u32>>();
+    /// assert_eq!(compressed.values().collect::<Vec<_>>(), vec![2, 64, 300]);
+    /// ```
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.firsts.len()).flat_map(move |block| self.decode(block))
+    }
+
+    /// Return an iterator over the members in ascending order that can be combined with other
+    /// ordered set iterators.  The members that are iterated over (as opposed to seeked past)
+    /// are stored in `arena`.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::{compressed::CompressedOrdListSet, OrdListSet};
+    /// use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, ValueArena};
+    ///
+    /// let compressed = (0..1000).collect::<CompressedOrdListSet<u32>>();
+    /// let list = OrdListSet::<u32>::from([5, 50, 500, 5000]);
+    /// let arena = ValueArena::default();
+    /// let iter = compressed.iter_in(&arena).intersection(list.iter());
+    /// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![5, 50, 500]);
+    /// ```
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<T>) -> CompressedOrdListSetIter<'a, T> {
+        CompressedOrdListSetIter {
+            set: self,
+            arena,
+            block: 0,
+            index: 0,
+            decoded: vec![],
+            decoded_block: None,
+            item: None,
+        }
+    }
+}
+
+impl<T: Compressible> Default for CompressedOrdListSet<T> {
+    fn default() -> Self {
+        Self::empty_set()
+    }
+}
+
+impl<T: Compressible> Clone for CompressedOrdListSet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            firsts: self.firsts.clone(),
+            offsets: self.offsets.clone(),
+            bytes: self.bytes.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Compressible> PartialEq for CompressedOrdListSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        // NB: the encoding is canonical
+        self.firsts == other.firsts && self.bytes == other.bytes
+    }
+}
+
+impl<T: Compressible> Eq for CompressedOrdListSet<T> {}
+
+impl<T: Compressible + Debug> Debug for CompressedOrdListSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.values()).finish()
+    }
+}
+
+impl<T: Compressible> FromIterator<T> for CompressedOrdListSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut members: Vec<T> = iter.into_iter().collect();
+        members.sort_unstable();
+        members.dedup();
+        Self::from_sorted(members)
+    }
+}
+
+impl<T: Compressible> From<&OrdListSet<T>> for CompressedOrdListSet<T> {
+    fn from(list: &OrdListSet<T>) -> Self {
+        Self::from_sorted(list.iter().copied())
+    }
+}
+
+impl<T: Compressible> From<&CompressedOrdListSet<T>> for OrdListSet<T> {
+    fn from(set: &CompressedOrdListSet<T>) -> Self {
+        Self {
+            members: set.values().collect(),
+        }
+    }
+}
+
+/// An iterator over the members of a `CompressedOrdListSet`.
+pub struct CompressedOrdListSetIter<'a, T> {
+    set: &'a CompressedOrdListSet<T>,
+    arena: &'a ValueArena<T>,
+    // The current block and the index of the next member within it
+    block: usize,
+    index: usize,
+    // The members of the most recently decoded block
+    decoded: Vec<T>,
+    decoded_block: Option<usize>,
+    // The next member (once it has been stored in the arena)
+    item: Option<&'a T>,
+}
+
+impl<'a, T> Clone for CompressedOrdListSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        // NB: the clone decodes blocks for itself (if and when it needs them)
+        Self {
+            set: self.set,
+            arena: self.arena,
+            block: self.block,
+            index: self.index,
+            decoded: vec![],
+            decoded_block: None,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a, T: Compressible> CompressedOrdListSetIter<'a, T> {
+    /// Returns the number of elements remaining in the iterator.
+    pub fn len(&self) -> usize {
+        self.set.len - self.offset()
+    }
+
+    /// Returns whether the iterator has any remaining elements.
+    pub fn is_empty(&self) -> bool {
+        self.offset() >= self.set.len
+    }
+
+    fn offset(&self) -> usize {
+        (self.block * BLOCK_LEN + self.index).min(self.set.len)
+    }
+
+    // The members of the current block (decoding it if necessary).
+    fn block(&mut self) -> &[T] {
+        if self.decoded_block != Some(self.block) {
+            self.decoded.clear();
+            self.decoded.extend(self.set.decode(self.block));
+            self.decoded_block = Some(self.block);
+        }
+        &self.decoded
+    }
+
+    // The next member (if any).
+    fn current(&mut self) -> Option<T> {
+        if self.offset() < self.set.len {
+            let index = self.index;
+            self.block().get(index).copied()
+        } else {
+            None
+        }
+    }
+
+    // Move to the first member of the current block that isn't `before` the target.
+    fn seek_within_block(&mut self, before: impl Fn(&T) -> bool) {
+        let block = self.block();
+        let index = block.partition_point(before);
+        let len = block.len();
+        self.index = index;
+        if index == len {
+            // NB: the next block's first member is greater than the target
+            self.block += 1;
+            self.index = 0;
+        }
+        self.item = None;
+    }
+}
+
+impl<'a, T: Compressible> Iterator for CompressedOrdListSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        self.index += 1;
+        if self.index == BLOCK_LEN {
+            self.block += 1;
+            self.index = 0;
+        }
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl<'a, T: Compressible> ExactSizeIterator for CompressedOrdListSetIter<'a, T> {}
+
+impl<'a, T: Compressible> PeepAdvanceIter<'a, T> for CompressedOrdListSetIter<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        if self.item.is_none() {
+            let member = self.current()?;
+            self.item = Some(self.arena.alloc(member));
+        }
+        self.item
+    }
+
+    /// Write a description of this iterator: `CompressedOrdListSetIter[len=<set length>]` or
+    /// (if `detailed`) `CompressedOrdListSetIter[pos=<position>, remaining=<items remaining>]`.
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(
+                f,
+                "CompressedOrdListSetIter[pos={}, remaining={}]",
+                self.offset(),
+                self.len()
+            )
+        } else {
+            write!(f, "CompressedOrdListSetIter[len={}]", self.set.len)
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Advance this iterator to the next item at or after the given item.  Implementation
+    /// binary searches the skip table for the target's block and then decodes (only) that
+    /// block.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::compressed::CompressedOrdListSet;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    ///
+    /// let set = (0..100_000).map(|i| i * 2).collect::<CompressedOrdListSet<u64>>();
+    /// let arena = ValueArena::default();
+    /// let mut iter = set.iter_in(&arena);
+    /// iter.advance_until(&150_001);
+    /// assert_eq!(iter.next(), Some(&150_002));
+    /// iter.advance_until(&20);
+    /// assert_eq!(iter.next(), Some(&150_004));
+    /// iter.advance_after(&199_996);
+    /// assert_eq!(iter.next(), Some(&199_998));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    fn advance_until(&mut self, t: &T) {
+        // Make sure we don't go backwards
+        if let Some(member) = self.current() {
+            if member < *t {
+                self.block = self.block.max(self.set.block_for(t));
+                self.seek_within_block(|member| member < t);
+            }
+        }
+    }
+
+    fn advance_after(&mut self, t: &T) {
+        // Make sure we don't go backwards
+        if let Some(member) = self.current() {
+            if member <= *t {
+                self.block = self.block.max(self.set.block_for(t));
+                self.seek_within_block(|member| member <= t);
+            }
+        }
+    }
+}
+
+impl<'a, T: Compressible> OrdSetIterSetOpsIterator<'a, T> for CompressedOrdListSetIter<'a, T> {}
+
+impl<'a, T: Compressible> fmt::Debug for CompressedOrdListSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T: Compressible> fmt::Display for CompressedOrdListSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let members = [0u64, 1, 127, 128, 300, 1 << 20, 1 << 40, u64::MAX];
+        let list = OrdListSet::from_iter(members.iter().copied().chain(1000..2000));
+        let set = CompressedOrdListSet::from(&list);
+        assert_eq!(set.len(), list.len());
+        assert_eq!(OrdListSet::from(&set), list);
+        let arena = ValueArena::default();
+        assert!(set.iter_in(&arena).eq(list.iter()));
+        assert!(set.values().eq(list.iter().copied()));
+        assert!(set.contains(&u64::MAX) && !set.contains(&2000));
+        assert_eq!(set, set.clone());
+        assert!(CompressedOrdListSet::<u64>::empty_set()
+            .iter_in(&arena)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn seeks_store_only_visited_members() {
+        let set = CompressedOrdListSet::<u32>::from_iter((0..10 * BLOCK_LEN as u32).map(|i| i * 5));
+        let arena = ValueArena::default();
+        let mut iter = set.iter_in(&arena);
+        assert_eq!(iter.peep(), Some(&0));
+        iter.advance_until(&(5 * 5 * BLOCK_LEN as u32 + 1));
+        assert_eq!(iter.next(), Some(&(5 * 5 * BLOCK_LEN as u32 + 5)));
+        assert_eq!(
+            format!("{:?}", iter),
+            "CompressedOrdListSetIter[pos=642, remaining=638]"
+        );
+        iter.advance_after(&(10 * 5 * BLOCK_LEN as u32 - 5));
+        assert_eq!(iter.next(), None);
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn bad_varints() {
+        let mut bytes = vec![];
+        encode_varint(&mut bytes, u64::MAX);
+        assert_eq!(decode_varint(&bytes), Some((u64::MAX, 10)));
+        assert_eq!(decode_varint(&bytes[..9]), None);
+        assert_eq!(decode_varint(&[0x80; 11]), None);
+        assert_eq!(
+            decode_varint(&[0xff; 9].iter().chain(&[2]).copied().collect::<Vec<_>>()),
+            None
+        );
+    }
+}
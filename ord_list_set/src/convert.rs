@@ -344,10 +344,7 @@ impl<T: Ord> FromIterator<T> for OrdListSet<T> {
     /// assert_eq!(vec, set.items(..));
     /// ```
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut members = Vec::new();
-        for m in iter {
-            members.push(m);
-        }
+        let mut members: Vec<T> = iter.into_iter().collect();
         members.sort_unstable();
         members.dedup();
         Self {
@@ -0,0 +1,46 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! The errors reported by the fallible APIs of this crate.  The text and binary formats
+//! (and deserialization) report unsorted or duplicated members with these too.
+
+use std::error::Error;
+
+use super::*;
+
+/// The reasons that members can't be used (as is) as the contents of a set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrdListSetError {
+    /// The member at `index` is less than its predecessor.
+    OutOfOrder { index: usize },
+    /// The member at `index` is equal to its predecessor.
+    Duplicate { index: usize },
+    /// The bytes aren't aligned for the member type.
+    Misaligned,
+    /// The number of bytes isn't a multiple of the size of the member type.
+    BadLength,
+}
+
+impl fmt::Display for OrdListSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use OrdListSetError::*;
+        match self {
+            OutOfOrder { index } => write!(f, "member {} is less than its predecessor", index),
+            Duplicate { index } => write!(f, "member {} is a duplicate of its predecessor", index),
+            Misaligned => write!(f, "bytes are not aligned for the member type"),
+            BadLength => write!(f, "byte count is not a multiple of the member size"),
+        }
+    }
+}
+
+impl Error for OrdListSetError {}
+
+// Check that `list` is sorted with no duplicates reporting the first member that isn't.
+pub(crate) fn check_sorted_and_no_dups<T: Ord>(list: &[T]) -> Result<(), OrdListSetError> {
+    for (index, pair) in list.windows(2).enumerate() {
+        match pair[0].cmp(&pair[1]) {
+            Ordering::Less => (),
+            Ordering::Equal => return Err(OrdListSetError::Duplicate { index: index + 1 }),
+            Ordering::Greater => return Err(OrdListSetError::OutOfOrder { index: index + 1 }),
+        }
+    }
+    Ok(())
+}
@@ -3,20 +3,35 @@
 
 use std::{
     cmp::Ordering,
-    collections::BTreeSet,
-    fmt::Debug,
-    iter::FromIterator,
+    collections::{btree_set, BTreeSet},
+    fmt::{self, Debug},
+    iter::{FromIterator, Peekable},
     ops::{BitAnd, BitOr, BitXor, Bound, RangeBounds, Sub},
 };
 
 use ord_set_iter_set_ops::{
-    are_disjoint, difference_next, difference_peep, intersection_next, intersection_peep,
-    left_is_proper_subset_of_right, left_is_proper_superset_of_right, left_is_subset_of_right,
-    left_is_superset_of_right, symmetric_difference_next, symmetric_difference_peep, union_next,
-    union_peep, OrdSetIterSetOpsIterator, PeepAdvanceIter,
+    are_disjoint, difference_next, difference_peep, difference_size_hint, fmt_binary_expr,
+    impl_expr_debug_display, impl_set_op_operators, intersection_next, intersection_peep,
+    intersection_size_hint, left_is_proper_subset_of_right, left_is_proper_superset_of_right,
+    left_is_subset_of_right, left_is_superset_of_right, symmetric_difference_next,
+    symmetric_difference_peep, symmetric_difference_size_hint, union_next, union_peep,
+    union_size_hint, OrdSetIterSetOpsIterator, PeepAdvanceIter, SeekResult,
 };
 
+pub mod binary;
+pub mod borrowed;
+pub mod builder;
+pub mod compressed;
 pub mod convert;
+pub mod error;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod shared;
+pub mod text;
+
+pub use error::OrdListSetError;
+
+use error::check_sorted_and_no_dups;
 
 /// An immutable set of items of type T ordered according to Ord (with no duplicates)
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -63,6 +78,46 @@ impl<T: Ord> OrdListSet<T> {
     pub fn empty_set() -> Self {
         Self::default()
     }
+
+    /// Create an `OrdListSet` from `members` that are expected to be already sorted with no
+    /// duplicates (without the sort and dedup of `From<Vec<T>>`).  The first member that
+    /// breaks this expectation is reported.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::{OrdListSet, OrdListSetError};
+    ///
+    /// let set = OrdListSet::try_from_sorted(vec![1, 3, 5]).unwrap();
+    /// assert_eq!(set.len(), 3);
+    /// assert_eq!(
+    ///     OrdListSet::try_from_sorted(vec![1, 3, 3, 2]),
+    ///     Err(OrdListSetError::Duplicate { index: 2 })
+    /// );
+    /// assert_eq!(
+    ///     OrdListSet::try_from_sorted(vec![1, 3, 2]),
+    ///     Err(OrdListSetError::OutOfOrder { index: 2 })
+    /// );
+    /// ```
+    pub fn try_from_sorted(members: Vec<T>) -> Result<Self, OrdListSetError> {
+        check_sorted_and_no_dups(&members)?;
+        Ok(Self {
+            members: members.into_boxed_slice(),
+        })
+    }
+
+    /// Create an `OrdListSet` from `members` without checking that they are sorted with no
+    /// duplicates.
+    ///
+    /// # Safety
+    ///
+    /// `members` must be sorted with no duplicates.  (Set operations and searches will give
+    /// nonsensical results if they aren't).
+    pub unsafe fn from_sorted_unchecked(members: Vec<T>) -> Self {
+        debug_assert!(is_sorted_and_no_dups(&members));
+        Self {
+            members: members.into_boxed_slice(),
+        }
+    }
 }
 
 impl<T: Ord> OrdListSet<T> {
@@ -77,7 +132,7 @@ impl<T: Ord> OrdListSet<T> {
     }
 
     /// Return an iterator over the members in the `OrdListSet` in ascending order.
-    pub fn iter(&self) -> OrdListSetIter<T> {
+    pub fn iter(&self) -> OrdListSetIter<'_, T> {
         OrdListSetIter {
             elements: &self.members,
             index: 0,
@@ -89,6 +144,135 @@ impl<T: Ord> OrdListSet<T> {
     }
 }
 
+// positional queries
+impl<T: Ord> OrdListSet<T> {
+    /// Return the number of members that are less than `item` (whether or not `item` is a
+    /// member).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.rank(&5), 0);
+    /// assert_eq!(set.rank(&20), 1);
+    /// assert_eq!(set.rank(&25), 2);
+    /// assert_eq!(set.rank(&99), 3);
+    /// ```
+    pub fn rank(&self, item: &T) -> usize {
+        self.members.partition_point(|member| member < item)
+    }
+
+    /// Return the index of `item` in the set (if it's a member).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.index_of(&20), Some(1));
+    /// assert_eq!(set.index_of(&25), None);
+    /// ```
+    pub fn index_of(&self, item: &T) -> Option<usize> {
+        self.members.binary_search(item).ok()
+    }
+
+    /// Return the member with rank `k` i.e. the member with `k` members less than it (the
+    /// inverse of `rank()` for members).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.select(set.rank(&20)), Some(&20));
+    /// assert_eq!(set.select(3), None);
+    /// ```
+    pub fn select(&self, k: usize) -> Option<&T> {
+        self.members.get(k)
+    }
+
+    /// Return the number of members in `range`.  Implementation is a binary search that is
+    /// O(log(n)).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = (0..100).map(|i| i * 2).collect::<OrdListSet<u32>>();
+    /// assert_eq!(set.count_in(10..20), 5);
+    /// assert_eq!(set.count_in(10..=20), 6);
+    /// assert_eq!(set.count_in(..), 100);
+    /// assert_eq!(set.count_in(150..), 25);
+    /// assert_eq!(set.count_in(20..10), 0);
+    /// ```
+    pub fn count_in(&self, range: impl RangeBounds<T>) -> usize {
+        self.view().item_items(range).len()
+    }
+
+    /// Return the greatest member that is less than or equal to `item` (if any).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.floor(&20), Some(&20));
+    /// assert_eq!(set.floor(&25), Some(&20));
+    /// assert_eq!(set.floor(&5), None);
+    /// ```
+    pub fn floor(&self, item: &T) -> Option<&T> {
+        let end = self.members.partition_point(|member| member <= item);
+        self.members[..end].last()
+    }
+
+    /// Return the least member that is greater than or equal to `item` (if any).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.ceiling(&20), Some(&20));
+    /// assert_eq!(set.ceiling(&25), Some(&30));
+    /// assert_eq!(set.ceiling(&35), None);
+    /// ```
+    pub fn ceiling(&self, item: &T) -> Option<&T> {
+        self.members.get(self.rank(item))
+    }
+
+    /// Return the greatest member that is less than `item` (if any).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.predecessor(&20), Some(&10));
+    /// assert_eq!(set.predecessor(&25), Some(&20));
+    /// assert_eq!(set.predecessor(&10), None);
+    /// ```
+    pub fn predecessor(&self, item: &T) -> Option<&T> {
+        self.members[..self.rank(item)].last()
+    }
+
+    /// Return the least member that is greater than `item` (if any).
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let set = OrdListSet::<u32>::from([10, 20, 30]);
+    /// assert_eq!(set.successor(&20), Some(&30));
+    /// assert_eq!(set.successor(&5), Some(&10));
+    /// assert_eq!(set.successor(&30), None);
+    /// ```
+    pub fn successor(&self, item: &T) -> Option<&T> {
+        let start = self.members.partition_point(|member| member <= item);
+        self.members.get(start)
+    }
+}
+
 fn is_sorted_and_no_dups<T: Ord>(list: &[T]) -> bool {
     if !list.is_empty() {
         let mut last = &list[0];
@@ -339,6 +523,10 @@ impl<'a, T: Ord> Iterator for Union<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         union_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        union_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Union<'a, T> {
@@ -346,6 +534,20 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Union<'a, T> {
         union_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Union", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l && r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target)
@@ -359,6 +561,8 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Union<'a, T> {
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Union<'a, T> {}
 
+impl_expr_debug_display!(Union);
+
 #[derive(Clone)]
 pub struct Intersection<'a, T: Ord> {
     left_iter: OrdListSetIter<'a, T>,
@@ -371,6 +575,10 @@ impl<'a, T: Ord> Iterator for Intersection<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         intersection_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        intersection_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Intersection<'a, T> {
@@ -378,6 +586,20 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Intersection<'a, T> {
         intersection_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Intersection", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l || r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target)
@@ -391,6 +613,8 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Intersection<'a, T> {
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Intersection<'a, T> {}
 
+impl_expr_debug_display!(Intersection);
+
 #[derive(Clone)]
 pub struct Difference<'a, T: Ord> {
     left_iter: OrdListSetIter<'a, T>,
@@ -403,6 +627,10 @@ impl<'a, T: Ord> Iterator for Difference<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         difference_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        difference_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Difference<'a, T> {
@@ -410,6 +638,20 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Difference<'a, T> {
         difference_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Difference", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        // only the left iterator's items matter
+        self.right_iter.restrict_end(end);
+        self.left_iter.restrict_end(end)
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target)
@@ -423,6 +665,8 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Difference<'a, T> {
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Difference<'a, T> {}
 
+impl_expr_debug_display!(Difference);
+
 #[derive(Clone)]
 pub struct SymmetricDifference<'a, T: Ord> {
     left_iter: OrdListSetIter<'a, T>,
@@ -435,6 +679,10 @@ impl<'a, T: Ord> Iterator for SymmetricDifference<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         symmetric_difference_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        symmetric_difference_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for SymmetricDifference<'a, T> {
@@ -442,6 +690,26 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for SymmetricDifference<'a,
         symmetric_difference_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(
+            f,
+            "SymmetricDifference",
+            self.left_iter,
+            self.right_iter,
+            detailed
+        )
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l && r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target)
@@ -455,6 +723,8 @@ impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for SymmetricDifference<'a,
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for SymmetricDifference<'a, T> {}
 
+impl_expr_debug_display!(SymmetricDifference);
+
 impl<'a, T: 'a + Ord + Clone> OrdListSet<T> {
     /// Visits the values representing the difference, i.e., all the values in `self` but not in
     /// `other`,without duplicates, in ascending order.
@@ -667,6 +937,20 @@ impl<T: Ord + Clone> BitOr<&OrdListSet<T>> for &OrdListSet<T> {
 /// let b = OrdListSet::<u32>::from([ 7, 8, 9, 10, 11]);
 /// assert!(!a.iter().is_disjoint(b.iter()));
 /// ```
+///
+/// The set operators (`|`, `&`, `-` and `^`) applied to iterators build lazy set operation
+/// iterators so that expressions are evaluated in a single streaming pass.
+/// ```
+/// use ord_list_set::OrdListSet;
+///
+/// let a = OrdListSet::<u32>::from([1, 2, 3, 5]);
+/// let b = OrdListSet::<u32>::from([2, 3, 4, 7]);
+/// let c = OrdListSet::<u32>::from([3, 4, 6]);
+/// let result: Vec<u32> = (a.iter() | b.iter() - c.iter()).cloned().collect();
+/// assert_eq!(result, [1, 2, 3, 5, 7]);
+/// let result: Vec<u32> = ((a.iter() & b.iter()) ^ c.iter()).cloned().collect();
+/// assert_eq!(result, [2, 4, 6]);
+/// ```
 #[derive(Default)]
 pub struct OrdListSetIter<'a, T: Ord> {
     elements: &'a [T],
@@ -746,12 +1030,38 @@ impl<'a, T: Ord> Iterator for OrdListSetIter<'a, T> {
         self.next()
     }
 
+    /// Returns the exact number of elements remaining in the iterator.
+    ///
+    /// Example.
+    ///
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let a = OrdListSet::<u32>::from([1, 7, 8, 9, 2, 3,]);
+    /// let mut iter = a.iter();
+    /// assert_eq!(iter.size_hint(), (6, Some(6)));
+    /// iter.nth(3);
+    /// assert_eq!(iter.size_hint(), (2, Some(2)));
+    /// iter.nth(4);
+    /// assert_eq!(iter.size_hint(), (0, Some(0)));
+    /// ```
     fn size_hint(&self) -> (usize, Option<usize>) {
-        if self.index < self.elements.len() {
-            (self.index, Some(self.elements.len() - self.index))
-        } else {
-            (self.index, None)
-        }
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: Ord> ExactSizeIterator for OrdListSetIter<'a, T> {}
+
+impl<'a, T: Ord> fmt::Debug for OrdListSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T: Ord> fmt::Display for OrdListSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
     }
 }
 
@@ -770,6 +1080,51 @@ impl<'a, T: Ord> OrdListSetIter<'a, T> {
     pub fn is_empty(&self) -> bool {
         self.index >= self.elements.len()
     }
+
+    /// Returns the number of the set's members that precede the next item (i.e. the rank of
+    /// the next item in the set).  The position can be restored later with `set_rank()`.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::PeepAdvanceIter;
+    ///
+    /// let a = OrdListSet::<u32>::from([1, 7, 8, 9, 2, 3,]);
+    /// let mut iter = a.iter();
+    /// iter.advance_until(&5);
+    /// assert_eq!(iter.rank(), a.rank(&5));
+    /// let rank = iter.rank();
+    /// assert_eq!(iter.by_ref().count(), 3);
+    /// assert_eq!(iter.rank(), 6);
+    /// iter.set_rank(rank);
+    /// assert_eq!(iter.next(), Some(&7));
+    /// ```
+    pub fn rank(&self) -> usize {
+        self.index.min(self.elements.len())
+    }
+
+    /// Reposition the iterator so that its next item is the member with rank `rank` (as
+    /// reported by `rank()`).  A rank beyond the end exhausts the iterator.
+    pub fn set_rank(&mut self, rank: usize) {
+        self.index = rank.min(self.elements.len());
+    }
+
+    // Search the remaining elements for `t` by galloping (doubling the probe distance until an
+    // element not less than `t` is passed) followed by a binary search of the last interval.
+    // The result is relative to the current index as per `binary_search()`.
+    fn gallop(&self, t: &T) -> Result<usize, usize> {
+        let remaining = &self.elements[self.index..];
+        let mut bound = 1;
+        while bound < remaining.len() && remaining[bound] < *t {
+            bound *= 2;
+        }
+        let start = bound / 2;
+        let end = remaining.len().min(bound + 1);
+        match remaining[start..end].binary_search(t) {
+            Ok(index) => Ok(start + index),
+            Err(index) => Err(start + index),
+        }
+    }
 }
 
 impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for OrdListSetIter<'a, T> {
@@ -791,8 +1146,69 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for OrdListSetIter<'a, T> {
         self.elements.get(self.index)
     }
 
+    /// Write a description of this iterator: `OrdListSetIter[len=<set length>]` or (if
+    /// `detailed`) `OrdListSetIter[pos=<position>, remaining=<items remaining>]`.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    ///
+    /// let a = OrdListSet::<u32>::from([1, 7, 8, 9, 2, 3,]);
+    /// let b = OrdListSet::<u32>::from([3, 4, 5, 6]);
+    /// let mut iter = a.iter();
+    /// iter.next();
+    /// assert_eq!(format!("{}", iter), "OrdListSetIter[len=6]");
+    /// assert_eq!(format!("{:?}", iter), "OrdListSetIter[pos=1, remaining=5]");
+    /// let iter = (iter | b.iter()) & a.intersection(&b);
+    /// assert_eq!(
+    ///     format!("{}", iter),
+    ///     "Intersection(Union(OrdListSetIter[len=6], OrdListSetIter[len=4]), Intersection(OrdListSetIter[len=6], OrdListSetIter[len=4]))"
+    /// );
+    /// ```
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(
+                f,
+                "OrdListSetIter[pos={}, remaining={}]",
+                self.rank(),
+                self.len()
+            )
+        } else {
+            write!(f, "OrdListSetIter[len={}]", self.elements.len())
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Cut this iterator short so that it produces no items beyond `end`.  Implementation is
+    /// a binary search that is O(log(n)) so the end is always pushed down.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::OrdSetIterSetOpsIterator;
+    ///
+    /// let a = OrdListSet::<u32>::from([1, 2, 3, 5, 7, 8, 9]);
+    /// let b = OrdListSet::<u32>::from([2, 4, 6, 8]);
+    /// let iter = (a.iter() | b.iter()).restrict(3..7);
+    /// assert!(iter.is_pushed_down());
+    /// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+    /// ```
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let len = match end {
+            Bound::Included(end) => self.elements.partition_point(|item| item <= end),
+            Bound::Excluded(end) => self.elements.partition_point(|item| item < end),
+            Bound::Unbounded => return true,
+        };
+        self.elements = &self.elements[..len];
+        self.index = self.index.min(len);
+        true
+    }
+
     /// Advance this iterator to the next item at or after the given item.
-    /// Implementation is O(log(n)).
+    /// Implementation is a galloping search that is O(log(d)) where d is the distance advanced.
     ///
     /// Example
     /// ```
@@ -812,7 +1228,7 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for OrdListSetIter<'a, T> {
         // Make sure we don't go backwards
         if let Some(item) = self.peep() {
             if item < t {
-                self.index += match self.elements[self.index..].binary_search(t) {
+                self.index += match self.gallop(t) {
                     Ok(index) => index,
                     Err(index) => index,
                 };
@@ -820,8 +1236,8 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for OrdListSetIter<'a, T> {
         }
     }
 
-    /// Advance this iterator to the next item at or after the given item.
-    /// Default implementation is O(n) but custom built implementations could be as good as O(log(n)).
+    /// Advance this iterator to the next item after the given item.
+    /// Implementation is a galloping search that is O(log(d)) where d is the distance advanced.
     ///
     /// Example
     /// ```
@@ -841,17 +1257,61 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for OrdListSetIter<'a, T> {
         // Make sure we don't go backwards
         if let Some(item) = self.peep() {
             if item <= t {
-                self.index += match self.elements[self.index..].binary_search(t) {
+                self.index += match self.gallop(t) {
                     Ok(index) => index + 1,
                     Err(index) => index,
                 };
             }
         }
     }
+
+    /// Advance this iterator to the next item at or after the given item and report whether
+    /// the item was found.  Implementation is a galloping search that is O(log(d)) where d is
+    /// the distance advanced.
+    ///
+    /// Example
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, SeekResult};
+    ///
+    /// let a = OrdListSet::<u32>::from([1, 7, 8, 9, 2, 3, 5]);
+    /// let mut iter = a.iter();
+    /// assert_eq!(iter.seek(&3), SeekResult::Found(&3));
+    /// assert_eq!(iter.seek(&6), SeekResult::Landed(&7));
+    /// assert_eq!(iter.seek(&1), SeekResult::Landed(&7));
+    /// assert_eq!(iter.seek(&10), SeekResult::Exhausted);
+    /// ```
+    fn seek(&mut self, t: &T) -> SeekResult<&'a T> {
+        match self.peep() {
+            // Make sure we don't go backwards
+            Some(item) if item < t => match self.gallop(t) {
+                Ok(index) => {
+                    self.index += index;
+                    SeekResult::Found(&self.elements[self.index])
+                }
+                Err(index) => {
+                    self.index += index;
+                    match self.peep() {
+                        Some(item) => SeekResult::Landed(item),
+                        None => SeekResult::Exhausted,
+                    }
+                }
+            },
+            item => SeekResult::classify(t, item),
+        }
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for OrdListSetIter<'a, T> {}
 
+// Set operators on the iterators build lazy set operation iterators (rather than sets)
+impl_set_op_operators!(OrdListSetIter);
+impl_set_op_operators!(Union);
+impl_set_op_operators!(Intersection);
+impl_set_op_operators!(Difference);
+impl_set_op_operators!(SymmetricDifference);
+impl_set_op_operators!(Peekable<btree_set::Iter<'a, T>> => OrdListSetIter<'a, T>);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -866,6 +1326,141 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rank_and_select() {
+        let set = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 3));
+        for item in 0..310 {
+            let rank = set.rank(&item);
+            assert_eq!(rank, set.iter().filter(|member| **member < item).count());
+            assert_eq!(set.index_of(&item).is_some(), set.contains(&item));
+            if let Some(index) = set.index_of(&item) {
+                assert_eq!(index, rank);
+                assert_eq!(set.select(index), Some(&item));
+            }
+            assert_eq!(
+                set.count_in(item..item + 10),
+                set.item_items(item..item + 10).len()
+            );
+            let mut iter = set.iter();
+            iter.advance_until(&item);
+            assert_eq!(iter.rank(), rank);
+        }
+        let mut iter = set.iter();
+        iter.set_rank(set.len() + 1);
+        assert_eq!(iter.rank(), set.len());
+        assert!(iter.collect::<Vec<_>>().is_empty());
+    }
+
+    #[test]
+    fn neighbours() {
+        let set = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 3));
+        let other = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 5));
+        let both = OrdListSet::<u32>::from(set.intersection(&other));
+        for item in 0..310 {
+            let below = set.iter().filter(|member| **member <= item).last();
+            assert_eq!(set.floor(&item), below);
+            assert_eq!(set.iter().floor(&item), below);
+            let below = set.iter().filter(|member| **member < item).last();
+            assert_eq!(set.predecessor(&item), below);
+            assert_eq!(set.iter().predecessor(&item), below);
+            let above = set.iter().find(|member| **member >= item);
+            assert_eq!(set.ceiling(&item), above);
+            assert_eq!(set.iter().ceiling(&item), above);
+            let above = set.iter().find(|member| **member > item);
+            assert_eq!(set.successor(&item), above);
+            assert_eq!(set.iter().successor(&item), above);
+            let expr = || set.iter() & other.iter();
+            assert_eq!(expr().floor(&item), both.floor(&item));
+            assert_eq!(expr().predecessor(&item), both.predecessor(&item));
+            assert_eq!(expr().ceiling(&item), both.ceiling(&item));
+            assert_eq!(expr().successor(&item), both.successor(&item));
+        }
+    }
+
+    #[test]
+    fn restrict_end_behind_position() {
+        let set = OrdListSet::<u32>::from([1, 2, 3, 4, 5]);
+        let mut iter = set.iter();
+        iter.nth(3);
+        assert!(iter.restrict_end(Bound::Excluded(&3)));
+        assert_eq!(iter.len(), 0);
+        assert!(iter.collect::<Vec<_>>().is_empty());
+    }
+
+    #[test]
+    fn advance_gallops() {
+        let set = OrdListSet::<u32>::from_iter((0..1000).map(|i| i * 3));
+        for start in [0, 1, 17, 300] {
+            for target in 0..3010 {
+                let mut iter = set.iter();
+                iter.nth(start);
+                iter.advance_until(&target);
+                let expected = set.iter().skip(start + 1).find(|item| **item >= target);
+                assert_eq!(iter.peep(), expected);
+                let mut iter = set.iter();
+                iter.nth(start);
+                iter.advance_after(&target);
+                let expected = set.iter().skip(start + 1).find(|item| **item > target);
+                assert_eq!(iter.peep(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn planner() {
+        use ord_set_iter_set_ops::{SeekStrategy, SetExpr};
+        let big = OrdListSet::<u32>::from_iter(0..1000);
+        let medium = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 7));
+        let small = OrdListSet::<u32>::from([3, 14, 500, 2000]);
+        let excluded = OrdListSet::<u32>::from([14]);
+        let plan = SetExpr::labelled("big", big.iter())
+            .intersection(SetExpr::labelled("medium", medium.iter()))
+            .intersection(SetExpr::labelled("small", small.iter()))
+            .difference(SetExpr::labelled("excluded", excluded.iter()))
+            .plan();
+        assert_eq!(
+            plan.explain(),
+            format!(
+                "Difference (est. 4)\n  Intersection (est. 4)\n    Leaf \"small\" (est. 4)\n    Leaf \"medium\" (est. 100, {})\n    Leaf \"big\" (est. 1000, {})\n  Leaf \"excluded\" (est. 1, {})\n",
+                SeekStrategy::Galloping,
+                SeekStrategy::Galloping,
+                SeekStrategy::LinearMerge
+            )
+        );
+        assert!(plan.iter().is_empty());
+        let plan = SetExpr::leaf(medium.iter())
+            .intersection(SetExpr::leaf(small.iter()))
+            .plan();
+        assert_eq!(plan.iter().cloned().collect::<Vec<_>>(), vec![14]);
+        assert!(plan
+            .explain()
+            .contains("Leaf \"OrdListSetIter<u32>\" (est. 4)"));
+    }
+
+    #[test]
+    fn instrumented() {
+        use ord_set_iter_set_ops::StatsCollector;
+        let big = OrdListSet::<u32>::from_iter(0..1000);
+        let small = OrdListSet::<u32>::from([10, 500, 999]);
+        let stats = StatsCollector::default();
+        let iter = small
+            .iter()
+            .instrumented("small", &stats)
+            .intersection(big.iter().instrumented("big", &stats));
+        assert_eq!(iter.count(), 3);
+        let report = stats.report();
+        let big_stats = report.get("big").unwrap();
+        assert_eq!(big_stats.advance_until_calls, 3);
+        assert_eq!(big_stats.seek_distance, 10 + 489 + 498);
+        assert_eq!(big_stats.max_seek_distance, 498);
+        assert_eq!(big_stats.unmeasured_seeks, 0);
+        assert_eq!(big_stats.estimated_comparisons, 3 + 4 + 9 + 9);
+        assert_eq!(report.dominant().unwrap().label, "big");
+        assert_eq!(report.to_string().lines().count(), 3);
+        stats.reset();
+        assert_eq!(stats.report().get("big").unwrap().total_calls(), 0);
+    }
+
     #[test]
     fn union() {
         let set1: OrdListSet<&str> = ["a", "b", "c"].iter().cloned().collect();
@@ -0,0 +1,163 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Serialization of `OrdListSet`s (as sequences) using `serde`.
+//!
+//! Deserialization of an `OrdListSet` trusts that the input was produced by serializing an
+//! `OrdListSet` and rejects input that isn't sorted with no duplicates (rather than paying for
+//! a sort).  Use the `lenient` module (via `#[serde(with = "...")]`) for input that may not be
+//! sorted.
+//!
+//! Example:
+//! ```
+//! use ord_list_set::OrdListSet;
+//!
+//! let set = OrdListSet::<u32>::from([3, 1, 2]);
+//! let json = serde_json::to_string(&set).unwrap();
+//! assert_eq!(json, "[1,2,3]");
+//! assert_eq!(serde_json::from_str::<OrdListSet<u32>>(&json).unwrap(), set);
+//! assert!(serde_json::from_str::<OrdListSet<u32>>("[1,3,2]").is_err());
+//! assert!(serde_json::from_str::<OrdListSet<u32>>("[1,2,2]").is_err());
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::*;
+
+// Don't trust size hints from the input for more than this many members.
+const MAX_PREALLOCATION: usize = 4096;
+
+impl<T: Ord + Serialize> Serialize for OrdListSet<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.members.iter())
+    }
+}
+
+struct OrdListSetVisitor<T> {
+    lenient: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<'de, T: Ord + Deserialize<'de>> Visitor<'de> for OrdListSetVisitor<T> {
+    type Value = OrdListSet<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.lenient {
+            write!(formatter, "a sequence")
+        } else {
+            write!(formatter, "a sorted sequence with no duplicates")
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let capacity = seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATION);
+        let mut members: Vec<T> = Vec::with_capacity(capacity);
+        while let Some(member) = seq.next_element::<T>()? {
+            if !self.lenient {
+                if let Some(last) = members.last() {
+                    let index = members.len();
+                    match member.cmp(last) {
+                        Ordering::Less => {
+                            return Err(A::Error::custom(OrdListSetError::OutOfOrder { index }))
+                        }
+                        Ordering::Equal => {
+                            return Err(A::Error::custom(OrdListSetError::Duplicate { index }))
+                        }
+                        Ordering::Greater => (),
+                    }
+                }
+            }
+            members.push(member);
+        }
+        if self.lenient {
+            members.sort_unstable();
+            members.dedup();
+        }
+        Ok(OrdListSet {
+            members: members.into_boxed_slice(),
+        })
+    }
+}
+
+impl<'de, T: Ord + Deserialize<'de>> Deserialize<'de> for OrdListSet<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(OrdListSetVisitor {
+            lenient: false,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// For use with `#[serde(with = "ord_list_set::serde_support::lenient")]` on fields whose
+/// serialized form may be unsorted or contain duplicates.  These are sorted and removed (as
+/// per `From<Vec<T>>`) when deserializing.
+///
+/// Example:
+/// ```
+/// use ord_list_set::OrdListSet;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Config {
+///     #[serde(with = "ord_list_set::serde_support::lenient")]
+///     ports: OrdListSet<u16>,
+/// }
+///
+/// let config: Config = serde_json::from_str(r#"{"ports": [443, 80, 8080, 80]}"#).unwrap();
+/// assert_eq!(config.ports, OrdListSet::from([80, 443, 8080]));
+/// assert_eq!(
+///     serde_json::to_string(&config).unwrap(),
+///     r#"{"ports":[80,443,8080]}"#
+/// );
+/// ```
+pub mod lenient {
+    use super::*;
+
+    pub fn serialize<T, S>(set: &OrdListSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Ord + Serialize,
+        S: Serializer,
+    {
+        set.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<OrdListSet<T>, D::Error>
+    where
+        T: Ord + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(OrdListSetVisitor {
+            lenient: true,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde() {
+        let set = OrdListSet::<String>::from(["b".to_string(), "a".to_string()]);
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["a","b"]"#);
+        assert_eq!(
+            serde_json::from_str::<OrdListSet<String>>(&json).unwrap(),
+            set
+        );
+        let error = serde_json::from_str::<OrdListSet<u8>>("[1, 2, 2]").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("member 2 is a duplicate of its predecessor"));
+        let error = serde_json::from_str::<OrdListSet<u8>>("[1, 3, 2]").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("member 2 is less than its predecessor"));
+        assert!(serde_json::from_str::<OrdListSet<u8>>("{}").is_err());
+        let mut deserializer = serde_json::Deserializer::from_str("[3, 1, 2, 1]");
+        let set: OrdListSet<u8> = lenient::deserialize(&mut deserializer).unwrap();
+        assert_eq!(set, OrdListSet::from([1, 2, 3]));
+    }
+}
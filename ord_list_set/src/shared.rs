@@ -0,0 +1,338 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets whose clones and subsets share their (reference counted) members.
+
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use super::borrowed::OrdListSetRef;
+use super::*;
+
+/// An immutable set (like `OrdListSet`) whose members are kept in a shared buffer.  Cloning
+/// the set, or taking a subset or tail of it, only copies a reference to the buffer (plus
+/// the offset and length of the members within it) rather than the members themselves.
+///
+/// # Examples
+///
+/// ```
+/// use ord_list_set::{shared::SharedOrdListSet, OrdListSet};
+///
+/// let set = (0..1000).collect::<SharedOrdListSet<u32>>();
+/// let mut tail = set.get_item_subset(500..);
+/// let mut sum = 0;
+/// while let Some((first, rest)) = tail.first_and_tail() {
+///     sum += first;
+///     tail = rest;
+/// }
+/// assert_eq!(sum, (500..1000).sum::<u32>());
+/// assert!(set.get_subset(10..20).shares_members_with(&set));
+/// assert_eq!(OrdListSet::from(&set.get_subset(10..13)), OrdListSet::from([10, 11, 12]));
+/// ```
+pub struct SharedOrdListSet<T: Ord> {
+    buffer: Arc<[T]>,
+    start: usize,
+    len: usize,
+}
+
+impl<T: Ord> Clone for SharedOrdListSet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: Arc::clone(&self.buffer),
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Ord> Default for SharedOrdListSet<T> {
+    fn default() -> Self {
+        Self::from(OrdListSet::default())
+    }
+}
+
+impl<T: Ord + Debug> Debug for SharedOrdListSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedOrdListSet")
+            .field("members", &self.as_slice())
+            .finish()
+    }
+}
+
+impl<T: Ord> PartialEq for SharedOrdListSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Ord> Eq for SharedOrdListSet<T> {}
+
+impl<T: Ord> PartialEq<OrdListSet<T>> for SharedOrdListSet<T> {
+    fn eq(&self, other: &OrdListSet<T>) -> bool {
+        self.as_slice() == &other.members[..]
+    }
+}
+
+impl<T: Ord> PartialOrd for SharedOrdListSet<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for SharedOrdListSet<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord + Hash> Hash for SharedOrdListSet<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
+
+impl<T: Ord> From<OrdListSet<T>> for SharedOrdListSet<T> {
+    fn from(set: OrdListSet<T>) -> Self {
+        let len = set.members.len();
+        Self {
+            buffer: set.members.into(),
+            start: 0,
+            len,
+        }
+    }
+}
+
+impl<T: Ord + Clone> From<&SharedOrdListSet<T>> for OrdListSet<T> {
+    /// Create an OrdListSet<T> by copying the members of a SharedOrdListSet<T>.
+    fn from(set: &SharedOrdListSet<T>) -> Self {
+        Self {
+            members: set.as_slice().into(),
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SharedOrdListSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(OrdListSet::from_iter(iter))
+    }
+}
+
+impl<T: Ord> SharedOrdListSet<T> {
+    /// Return number of members in this set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The members (in ascending order).
+    pub fn as_slice(&self) -> &[T] {
+        &self.buffer[self.start..self.start + self.len]
+    }
+
+    /// A (`Copy`) view of this set.
+    pub fn view(&self) -> OrdListSetRef<'_, T> {
+        OrdListSetRef {
+            members: self.as_slice(),
+        }
+    }
+
+    /// Return an iterator over the members in ascending order.
+    pub fn iter(&self) -> OrdListSetIter<'_, T> {
+        self.view().iter()
+    }
+
+    /// Do `self` and `other` share the same buffer of members?
+    pub fn shares_members_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
+
+    ///Returns true if the set contains an element equal to the value.
+    pub fn contains(&self, item: &T) -> bool {
+        self.as_slice().binary_search(item).is_ok()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    /// Returns a reference to a subslice of the set's elements using indices.
+    pub fn items(&self, range: impl RangeBounds<usize>) -> &[T] {
+        self.view().items(range)
+    }
+
+    /// Returns a reference to a subslice of the set's elements using items.
+    pub fn item_items(&self, range: impl RangeBounds<T>) -> &[T] {
+        self.view().item_items(range)
+    }
+
+    // The subset of the members from `start` to `end` (sharing the buffer).
+    fn subset(&self, start: usize, end: usize) -> Self {
+        Self {
+            buffer: Arc::clone(&self.buffer),
+            start: self.start + start,
+            len: end - start,
+        }
+    }
+
+    /// Returns a (shared) subset using indices.  Indices that are out of range (as per
+    /// `items()`) give an empty set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::{shared::SharedOrdListSet, OrdListSet};
+    ///
+    /// let set = SharedOrdListSet::from(OrdListSet::from(["a", "d", "f", "h", "j", "k", "l"]));
+    ///
+    /// assert!(set.get_subset(set.len()..).is_empty());
+    /// assert!(set.get_subset(5..20).is_empty());
+    /// assert_eq!(set.get_subset(..=2).as_slice(), ["a", "d", "f"]);
+    /// assert_eq!(set.get_subset(1..5).get_subset(1..).as_slice(), ["f", "h", "j"]);
+    /// ```
+    pub fn get_subset(&self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(start) => Some(*start),
+            Bound::Excluded(start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.checked_add(1),
+            Bound::Excluded(end) => Some(*end),
+            Bound::Unbounded => Some(self.len),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end && end <= self.len => self.subset(start, end),
+            _ => self.subset(0, 0),
+        }
+    }
+
+    /// Returns a (shared) subset using items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::{shared::SharedOrdListSet, OrdListSet};
+    ///
+    /// let set = SharedOrdListSet::from(OrdListSet::from(["a", "d", "f", "h", "j", "k", "l"]));
+    ///
+    /// assert!(set.get_item_subset("m"..).is_empty());
+    /// assert_eq!(set.get_item_subset(..="g").as_slice(), ["a", "d", "f"]);
+    /// assert_eq!(set.get_item_subset("c".."k").as_slice(), ["d", "f", "h", "j"]);
+    /// ```
+    pub fn get_item_subset(&self, range: impl RangeBounds<T>) -> Self {
+        let members = self.as_slice();
+        let start = match range.start_bound() {
+            Bound::Included(start) => members.partition_point(|member| member < start),
+            Bound::Excluded(start) => members.partition_point(|member| member <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => members.partition_point(|member| member <= end),
+            Bound::Excluded(end) => members.partition_point(|member| member < end),
+            Bound::Unbounded => self.len,
+        };
+        self.subset(start, end.max(start))
+    }
+
+    /// Returns a reference to the first element in the set, if any.
+    pub fn first(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    /// Returns the first element and a (shared) set of the remaining members.
+    pub fn first_and_tail(&self) -> Option<(&T, Self)> {
+        let first = self.first()?;
+        Some((first, self.subset(1, self.len)))
+    }
+
+    /// Returns a reference to the last element in the set, if any.
+    pub fn last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+}
+
+impl<T: Ord + Clone> SharedOrdListSet<T> {
+    /// Visits the values in `self` but not in `other` in ascending order.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T> {
+        self.view().difference(&other.view())
+    }
+
+    /// Visits the values in both `self` and `other` in ascending order.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T> {
+        self.view().intersection(&other.view())
+    }
+
+    /// Visits the values in `self` or `other` but not in both in ascending order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T> {
+        self.view().symmetric_difference(&other.view())
+    }
+
+    /// Visits the values in `self` or `other` in ascending order.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T> {
+        self.view().union(&other.view())
+    }
+
+    /// Is `other` disjoint from this set?
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.view().is_disjoint(&other.view())
+    }
+
+    /// Is this set a proper subset of `other`?
+    pub fn is_proper_subset(&self, other: &Self) -> bool {
+        self.view().is_proper_subset(&other.view())
+    }
+
+    /// Is this set a proper superset of `other`?
+    pub fn is_proper_superset(&self, other: &Self) -> bool {
+        self.view().is_proper_superset(&other.view())
+    }
+
+    /// Is this set a subset of `other`?
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.view().is_subset(&other.view())
+    }
+
+    /// Is this set a superset of `other`?
+    pub fn is_superset(&self, other: &Self) -> bool {
+        self.view().is_superset(&other.view())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsets_share_members() {
+        let owned = OrdListSet::<u32>::from_iter((0..100).map(|i| i * 2));
+        let set = SharedOrdListSet::from(owned.clone());
+        assert_eq!(set, owned);
+        for (start, end) in [(0, 0), (0, 100), (10, 20), (99, 100), (50, 40), (90, 200)] {
+            let subset = set.get_subset(start..end);
+            assert!(subset.shares_members_with(&set));
+            assert_eq!(subset.as_slice(), owned.items(start..end));
+            assert_eq!(subset, owned.get_subset(start..end));
+        }
+        let middle = set.get_item_subset(51..=150);
+        assert_eq!(middle, owned.get_item_subset(51..=150));
+        assert_eq!(middle.get_item_subset(..60), owned.get_item_subset(51..60));
+        assert!(middle.get_item_subset(300..).is_empty());
+        let clone = middle.clone();
+        assert!(clone.shares_members_with(&set));
+        assert!(clone.is_subset(&set) && clone.is_proper_subset(&set));
+        assert!(set.is_superset(&clone) && !clone.is_superset(&set));
+        assert_eq!(set.difference(&middle).count() + middle.len(), set.len());
+        assert_eq!(
+            set.intersection(&middle).cloned().collect::<Vec<_>>(),
+            middle.as_slice()
+        );
+        let (first, tail) = middle.first_and_tail().unwrap();
+        assert_eq!(*first, 52);
+        assert_eq!(tail.first(), Some(&54));
+        assert_eq!(tail.last(), Some(&150));
+        assert!(tail.is_disjoint(&SharedOrdListSet::from_iter([1, 3, 52])));
+        assert!(SharedOrdListSet::<u8>::default().first_and_tail().is_none());
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! A human readable text format for `OrdListSet`s.
+//!
+//! Sets are written as their members (in ascending order) separated by commas and enclosed
+//! in braces, e.g. `{a, b, c}`.  For integer members, runs of three or more consecutive
+//! values are abbreviated to inclusive ranges, e.g. `{1..=5, 9}`.  The alternate form
+//! (`{:#}`) puts each member (or range) on a line of its own.
+//!
+//! String (and character) members that are empty, have leading or trailing white space or
+//! contain any of `,{}"\` are written in double quotes with `"` and `\` escaped by a `\`.
+//!
+//! Parsing (via `str::parse()`) accepts members in any order and ignores duplicates;
+//! `OrdListSet::from_str_strict()` rejects them instead.  As ranges are expanded when parsed,
+//! they may contain at most `MAX_RANGE_MEMBERS` members in total.
+//!
+//! Example:
+//! ```
+//! use ord_list_set::OrdListSet;
+//!
+//! let set = OrdListSet::<u32>::from([9, 1, 2, 3, 4, 5, 11, 12]);
+//! assert_eq!(set.to_string(), "{1..=5, 9, 11, 12}");
+//! assert_eq!(format!("{:#}", set), "{\n    1..=5,\n    9,\n    11,\n    12,\n}");
+//! assert_eq!("{1..=5, 9, 11, 12}".parse::<OrdListSet<u32>>().unwrap(), set);
+//! assert_eq!("{12, 3..=5, 9, 11, 1, 2, 5}".parse::<OrdListSet<u32>>().unwrap(), set);
+//! assert!(OrdListSet::<u32>::from_str_strict("{12, 3..=5, 9, 11, 1, 2}").is_err());
+//!
+//! let words = OrdListSet::<String>::from(["a, b".to_string(), "c".to_string()]);
+//! assert_eq!(words.to_string(), r#"{"a, b", c}"#);
+//! assert_eq!(words.to_string().parse::<OrdListSet<String>>().unwrap(), words);
+//! ```
+
+use std::error::Error;
+use std::str::FromStr;
+
+use super::*;
+
+/// The maximum number of members that the ranges in a text may expand to.
+pub const MAX_RANGE_MEMBERS: usize = 1 << 20;
+
+/// Element types that can be written in (and, if they implement `FromStr`, read from) the
+/// text format.
+pub trait TextElement: Ord + fmt::Display + Sized {
+    /// Whether runs of consecutive members are written (and may be read) as ranges.
+    const HAS_RANGES: bool = false;
+
+    /// The value immediately after `self` (if any).  Only used if `HAS_RANGES` is `true`.
+    fn successor(&self) -> Option<Self> {
+        None
+    }
+
+    /// Write `self` as a member of a set.  Default implementation uses `Display`.
+    fn write_member(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Write `text` quoted if it wouldn't otherwise be parsed back as itself.
+fn write_text_member(text: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if text.is_empty() || text.trim() != text || text.contains([',', '{', '}', '"', '\\']) {
+        write!(f, "\"")?;
+        for c in text.chars() {
+            if matches!(c, '"' | '\\') {
+                write!(f, "\\")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, "\"")
+    } else {
+        write!(f, "{}", text)
+    }
+}
+
+macro_rules! impl_text_element_for_ints {
+    ( $( $int:ty ),* ) => {
+        $(
+            impl TextElement for $int {
+                const HAS_RANGES: bool = true;
+
+                fn successor(&self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+            }
+        )*
+    };
+}
+
+impl_text_element_for_ints!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl TextElement for char {
+    fn write_member(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_text_member(self.encode_utf8(&mut [0; 4]), f)
+    }
+}
+
+impl TextElement for String {
+    fn write_member(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_text_member(self, f)
+    }
+}
+
+impl TextElement for &str {
+    fn write_member(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_text_member(self, f)
+    }
+}
+
+/// The errors that can occur when parsing an `OrdListSet` from text.  Entries (members or
+/// ranges) are numbered from zero in the order that they appear in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseOrdListSetError {
+    /// The text isn't enclosed in braces.
+    MissingBraces,
+    /// The entry at `entry` couldn't be parsed as a member.
+    BadMember { entry: usize, text: String },
+    /// The range at `entry` ends before it starts.
+    BadRange { entry: usize },
+    /// The ranges up to (and including) `entry` expand to more than `MAX_RANGE_MEMBERS`.
+    RangeTooLarge { entry: usize },
+    /// (Strict mode only) the entry at `entry` is out of order with, or duplicates, its
+    /// predecessor.  `source` gives the index of the offending member in the expanded set.
+    Unordered {
+        entry: usize,
+        source: OrdListSetError,
+    },
+}
+
+impl fmt::Display for ParseOrdListSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ParseOrdListSetError::*;
+        match self {
+            MissingBraces => write!(f, "set is not enclosed in braces"),
+            BadMember { entry, text } => {
+                write!(f, "entry {} ({:?}) is not a valid member", entry, text)
+            }
+            BadRange { entry } => write!(f, "range at entry {} ends before it starts", entry),
+            RangeTooLarge { entry } => write!(
+                f,
+                "ranges up to entry {} have more than {} members",
+                entry, MAX_RANGE_MEMBERS
+            ),
+            Unordered { entry, source } => write!(f, "entry {}: {}", entry, source),
+        }
+    }
+}
+
+impl Error for ParseOrdListSetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseOrdListSetError::Unordered { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl<T: TextElement> fmt::Display for OrdListSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty = f.alternate();
+        write!(f, "{{")?;
+        let mut index = 0;
+        while index < self.members.len() {
+            let start = index;
+            if T::HAS_RANGES {
+                while index + 1 < self.members.len()
+                    && self.members[index].successor().as_ref() == Some(&self.members[index + 1])
+                {
+                    index += 1;
+                }
+                // only abbreviate runs of three or more
+                if index - start < 2 {
+                    index = start;
+                }
+            }
+            if pretty {
+                write!(f, "\n    ")?;
+            } else if start > 0 {
+                write!(f, ", ")?;
+            }
+            self.members[start].write_member(f)?;
+            if index > start {
+                write!(f, "..=")?;
+                self.members[index].write_member(f)?;
+            }
+            if pretty {
+                write!(f, ",")?;
+            }
+            index += 1;
+        }
+        if pretty && !self.members.is_empty() {
+            writeln!(f)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<T: TextElement + FromStr> OrdListSet<T> {
+    fn parse_text(text: &str, strict: bool) -> Result<Self, ParseOrdListSetError> {
+        use ParseOrdListSetError::*;
+        let text = text.trim();
+        let inner = text
+            .strip_prefix('{')
+            .and_then(|text| text.strip_suffix('}'))
+            .ok_or(MissingBraces)?;
+        let mut members: Vec<T> = vec![];
+        let mut entries = split_entries(inner).map_err(|entry| BadMember {
+            entry,
+            text: inner.to_string(),
+        })?;
+        // allow an empty set and a trailing comma (as per the alternate form)
+        if entries.last() == Some(&"") {
+            entries.pop();
+        }
+        let parse = |entry: usize, text: &str| {
+            let member = match unquote(text) {
+                Some(unquoted) => unquoted.parse::<T>(),
+                None => text.parse::<T>(),
+            };
+            member.map_err(|_| BadMember {
+                entry,
+                text: text.to_string(),
+            })
+        };
+        let mut range_members = 0;
+        for (entry, text) in entries.into_iter().enumerate() {
+            let (first, last) = match text.split_once("..=") {
+                Some((start, end)) if T::HAS_RANGES => {
+                    let (start, end) = (parse(entry, start.trim())?, parse(entry, end.trim())?);
+                    if end < start {
+                        return Err(BadRange { entry });
+                    }
+                    (start, Some(end))
+                }
+                _ => (parse(entry, text)?, None),
+            };
+            if strict {
+                if let Some(previous) = members.last() {
+                    let index = members.len();
+                    match first.cmp(previous) {
+                        Ordering::Less => {
+                            let source = OrdListSetError::OutOfOrder { index };
+                            return Err(Unordered { entry, source });
+                        }
+                        Ordering::Equal => {
+                            let source = OrdListSetError::Duplicate { index };
+                            return Err(Unordered { entry, source });
+                        }
+                        Ordering::Greater => (),
+                    }
+                }
+            }
+            members.push(first);
+            if let Some(last) = last {
+                while members[members.len() - 1] < last {
+                    range_members += 1;
+                    if range_members > MAX_RANGE_MEMBERS {
+                        return Err(RangeTooLarge { entry });
+                    }
+                    match members[members.len() - 1].successor() {
+                        Some(next) => members.push(next),
+                        None => break,
+                    }
+                }
+            }
+        }
+        if !strict {
+            members.sort_unstable();
+            members.dedup();
+        }
+        Ok(Self {
+            members: members.into_boxed_slice(),
+        })
+    }
+
+    /// Parse `text` (as per `str::parse()`) but report members that are out of order or
+    /// duplicated rather than accepting them.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::{OrdListSet, OrdListSetError};
+    /// use ord_list_set::text::ParseOrdListSetError;
+    ///
+    /// assert_eq!(OrdListSet::<i8>::from_str_strict("{-3..=-1, 4}").unwrap().len(), 4);
+    /// assert_eq!(
+    ///     OrdListSet::<i8>::from_str_strict("{1, 5, 3}"),
+    ///     Err(ParseOrdListSetError::Unordered {
+    ///         entry: 2,
+    ///         source: OrdListSetError::OutOfOrder { index: 2 }
+    ///     })
+    /// );
+    /// assert_eq!(
+    ///     OrdListSet::<i8>::from_str_strict("{1..=5, 5}"),
+    ///     Err(ParseOrdListSetError::Unordered {
+    ///         entry: 1,
+    ///         source: OrdListSetError::Duplicate { index: 5 }
+    ///     })
+    /// );
+    /// ```
+    pub fn from_str_strict(text: &str) -> Result<Self, ParseOrdListSetError> {
+        Self::parse_text(text, true)
+    }
+}
+
+impl<T: TextElement + FromStr> FromStr for OrdListSet<T> {
+    type Err = ParseOrdListSetError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse_text(text, false)
+    }
+}
+
+// Split `text` at the commas that aren't within quotes (trimming the entries) or return the
+// number of the entry containing an unterminated quote.
+fn split_entries(text: &str) -> Result<Vec<&str>, usize> {
+    let mut entries = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted {
+            match c {
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => (),
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == ',' {
+            entries.push(text[start..index].trim());
+            start = index + 1;
+        }
+    }
+    if quoted {
+        return Err(entries.len());
+    }
+    entries.push(text[start..].trim());
+    Ok(entries)
+}
+
+// The contents of `text` if it's (wholly) a quoted string.
+fn unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next()?),
+            '"' => return None,
+            _ => unquoted.push(c),
+        }
+    }
+    Some(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let set = OrdListSet::<i32>::from([-2, -1, 0, 1, 7, 8, 10, 11, 12, 13]);
+        assert_eq!(set.to_string(), "{-2..=1, 7, 8, 10..=13}");
+        assert_eq!(set.to_string().parse::<OrdListSet<i32>>().unwrap(), set);
+        assert_eq!(
+            format!("{:#}", set).parse::<OrdListSet<i32>>().unwrap(),
+            set
+        );
+        assert_eq!(
+            OrdListSet::<i32>::from_str_strict(&set.to_string()).unwrap(),
+            set
+        );
+        assert_eq!(OrdListSet::<u8>::default().to_string(), "{}");
+        assert_eq!(format!("{:#}", OrdListSet::<u8>::default()), "{}");
+        assert_eq!(
+            " { } ".parse::<OrdListSet<u8>>().unwrap(),
+            OrdListSet::default()
+        );
+        assert_eq!("{250..=255}".parse::<OrdListSet<u8>>().unwrap().len(), 6);
+        let words = OrdListSet::<&str>::from(["b", "c", "a"]);
+        assert_eq!(words.to_string(), "{a, b, c}");
+        assert_eq!(
+            "{c, a, b, a}"
+                .parse::<OrdListSet<String>>()
+                .unwrap()
+                .to_string(),
+            "{a, b, c}"
+        );
+        assert_eq!("{a..=c}".parse::<OrdListSet<String>>().unwrap().len(), 1);
+        let words: OrdListSet<String> =
+            ["", " a", "b, c", "{d}", "\"e\"", "f\\g", "..=", "h", "i\"j"]
+                .iter()
+                .map(|word| word.to_string())
+                .collect();
+        assert_eq!(
+            words.to_string(),
+            r#"{"", " a", "\"e\"", ..=, "b, c", "f\\g", h, "i\"j", "{d}"}"#
+        );
+        for text in [words.to_string(), format!("{:#}", words)] {
+            assert_eq!(text.parse::<OrdListSet<String>>().unwrap(), words);
+            assert_eq!(OrdListSet::<String>::from_str_strict(&text).unwrap(), words);
+        }
+        let chars = OrdListSet::<char>::from([',', 'a', '"']);
+        assert_eq!(
+            chars.to_string().parse::<OrdListSet<char>>().unwrap(),
+            chars
+        );
+    }
+
+    #[test]
+    fn errors() {
+        use ParseOrdListSetError::*;
+        assert_eq!("1, 2".parse::<OrdListSet<u8>>(), Err(MissingBraces));
+        assert_eq!(
+            "{1, x}".parse::<OrdListSet<u8>>(),
+            Err(BadMember {
+                entry: 1,
+                text: "x".to_string()
+            })
+        );
+        assert!("{1,, 2}".parse::<OrdListSet<u8>>().is_err());
+        assert!("{,}".parse::<OrdListSet<u8>>().is_err());
+        assert!(r#"{"a", "b}"#.parse::<OrdListSet<String>>().is_err());
+        assert!(r#"{"a"b"}"#.parse::<OrdListSet<String>>().is_err());
+        assert_eq!(
+            "{5..=1}".parse::<OrdListSet<u8>>(),
+            Err(BadRange { entry: 0 })
+        );
+        assert_eq!(
+            "{0..=18446744073709551615}".parse::<OrdListSet<u64>>(),
+            Err(RangeTooLarge { entry: 0 })
+        );
+        assert_eq!(
+            "{0..=1000000, 2000000..=2100000}".parse::<OrdListSet<u64>>(),
+            Err(RangeTooLarge { entry: 1 })
+        );
+        assert_eq!(
+            OrdListSet::<u8>::from_str_strict("{1..=5, 3}"),
+            Err(Unordered {
+                entry: 1,
+                source: OrdListSetError::OutOfOrder { index: 5 }
+            })
+        );
+        assert_eq!(
+            OrdListSet::<u8>::from_str_strict("{1, 2, 2}"),
+            Err(Unordered {
+                entry: 2,
+                source: OrdListSetError::Duplicate { index: 2 }
+            })
+        );
+        let error = OrdListSet::<u8>::from_str_strict("{3, 1}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "entry 1: member 1 is less than its predecessor"
+        );
+        assert!(error.source().is_some());
+    }
+}
@@ -0,0 +1,621 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets of discrete items (e.g. integer IDs or IP addresses) stored as disjoint sorted
+//! half-open ranges.
+
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::{BitAnd, BitOr, BitXor, Range, Sub};
+
+use ord_list_set::OrdListSet;
+use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+/// Types whose items can be counted and stepped through (and hence stored as ranges).
+pub trait Discrete: Ord + Clone {
+    /// The number of items from `self` up to (but not including) `other`, which mustn't be
+    /// less than `self`.
+    fn distance_to(&self, other: &Self) -> u128;
+
+    /// The item `steps` items after `self` (if there is one).
+    fn forward(&self, steps: u128) -> Option<Self>;
+}
+
+macro_rules! impl_discrete {
+    ($($int:ty),+) => {
+        $(
+            impl Discrete for $int {
+                fn distance_to(&self, other: &Self) -> u128 {
+                    (*other as i128 - *self as i128) as u128
+                }
+
+                fn forward(&self, steps: u128) -> Option<Self> {
+                    let steps = i128::try_from(steps).ok()?;
+                    <$int>::try_from((*self as i128).checked_add(steps)?).ok()
+                }
+            }
+        )+
+    };
+}
+
+impl_discrete!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl Discrete for Ipv4Addr {
+    fn distance_to(&self, other: &Self) -> u128 {
+        u32::from(*self).distance_to(&u32::from(*other))
+    }
+
+    fn forward(&self, steps: u128) -> Option<Self> {
+        u32::from(*self).forward(steps).map(Ipv4Addr::from)
+    }
+}
+
+impl Discrete for Ipv6Addr {
+    fn distance_to(&self, other: &Self) -> u128 {
+        u128::from(*other) - u128::from(*self)
+    }
+
+    fn forward(&self, steps: u128) -> Option<Self> {
+        u128::from(*self).checked_add(steps).map(Ipv6Addr::from)
+    }
+}
+
+/// The error returned when converting a set that contains the maximum value of its type
+/// (which can't be the member of an `OrdRangeSet`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaximumValueError;
+
+impl fmt::Display for MaximumValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the maximum value of a type can't be a member of a range set"
+        )
+    }
+}
+
+impl Error for MaximumValueError {}
+
+/// A set stored as disjoint, non adjacent, sorted half-open ranges.  As with `Range`, the
+/// maximum value of a type can't be a member.
+///
+/// # Examples
+///
+/// ```
+/// use ord_range_set::OrdRangeSet;
+///
+/// let a = OrdRangeSet::<u32>::from([0..10, 20..30]);
+/// let b = OrdRangeSet::<u32>::from([5..25]);
+/// assert_eq!((&a | &b).ranges(), [0..30]);
+/// assert_eq!((&a & &b).ranges(), [5..10, 20..25]);
+/// assert_eq!((&a - &b).ranges(), [0..5, 25..30]);
+/// assert_eq!((&a ^ &b).ranges(), [0..5, 10..20, 25..30]);
+/// assert_eq!(a.len(), 20);
+/// ```
+#[derive(Clone)]
+pub struct OrdRangeSet<T> {
+    ranges: Box<[Range<T>]>,
+    // The number of items before each range
+    offsets: Box<[u128]>,
+    len: u128,
+}
+
+impl<T: Discrete> OrdRangeSet<T> {
+    pub fn empty_set() -> Self {
+        Self::from_normalized(vec![])
+    }
+
+    fn from_normalized(ranges: Vec<Range<T>>) -> Self {
+        let mut len = 0u128;
+        let offsets = ranges
+            .iter()
+            .map(|range| {
+                let offset = len;
+                len += range.start.distance_to(&range.end);
+                offset
+            })
+            .collect();
+        Self {
+            ranges: ranges.into_boxed_slice(),
+            offsets,
+            len,
+        }
+    }
+
+    // Sort `ranges`, drop empty ones and merge those that overlap or are adjacent.
+    fn normalized(mut ranges: Vec<Range<T>>) -> Vec<Range<T>> {
+        ranges.retain(|range| range.start < range.end);
+        ranges.sort_unstable_by(|a, b| a.start.cmp(&b.start));
+        let mut normalized: Vec<Range<T>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match normalized.last_mut() {
+                Some(last) if range.start <= last.end => {
+                    if range.end > last.end {
+                        last.end = range.end;
+                    }
+                }
+                _ => normalized.push(range),
+            }
+        }
+        normalized
+    }
+
+    /// The number of items in the set.
+    pub fn len(&self) -> u128 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The (disjoint, non adjacent and sorted) ranges that make up the set.
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    /// Returns `true` if the set contains the specified item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_range_set::OrdRangeSet;
+    ///
+    /// let set = OrdRangeSet::<u32>::from([0..10, 20..30]);
+    /// assert!(set.contains(&9));
+    /// assert!(!set.contains(&10));
+    /// assert!(set.contains(&20));
+    /// ```
+    pub fn contains(&self, item: &T) -> bool {
+        let index = self.ranges.partition_point(|range| range.end <= *item);
+        self.ranges
+            .get(index)
+            .is_some_and(|range| range.start <= *item)
+    }
+
+    /// An iterator over the items in the set that expands the ranges lazily so that the set
+    /// can be combined with other ordered set iterators.  The items that are iterated over
+    /// (as opposed to seeked past) are stored in `arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_range_set::OrdRangeSet;
+    /// use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, ValueArena};
+    ///
+    /// let ids = OrdRangeSet::<u32>::from([0..1_000_000]);
+    /// let list = OrdListSet::<u32>::from([3, 999_999, 1_000_000]);
+    /// let arena = ValueArena::default();
+    /// let iter = ids.iter_in(&arena).intersection(list.iter());
+    /// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![3, 999_999]);
+    /// // only the items that were visited (not the million in the range) are stored
+    /// assert!(arena.len() < 5);
+    /// ```
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<T>) -> OrdRangeSetIter<'a, T> {
+        OrdRangeSetIter {
+            set: self,
+            arena,
+            index: 0,
+            offset: 0,
+            item: None,
+        }
+    }
+
+    /// The items in the set (in ascending order).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_range_set::OrdRangeSet;
+    ///
+    /// let set = OrdRangeSet::<u32>::from([0..3, 7..9]);
+    /// assert_eq!(set.values().collect::<Vec<_>>(), vec![0, 1, 2, 7, 8]);
+    /// ```
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        self.ranges.iter().flat_map(|range| {
+            (0..range.start.distance_to(&range.end))
+                .map(move |steps| range.start.forward(steps).expect("within range"))
+        })
+    }
+
+    // Sweep over the boundaries of both sets keeping the items for which `keep(in self, in
+    // other)` is true.
+    fn combine(&self, other: &Self, keep: impl Fn(bool, bool) -> bool) -> Self {
+        let (mut i, mut j) = (0, 0);
+        let (mut in_self, mut in_other) = (false, false);
+        let mut start: Option<T> = None;
+        let mut ranges = vec![];
+        loop {
+            let self_next = self
+                .ranges
+                .get(i)
+                .map(|range| if in_self { &range.end } else { &range.start });
+            let other_next =
+                other
+                    .ranges
+                    .get(j)
+                    .map(|range| if in_other { &range.end } else { &range.start });
+            let point = match (self_next, other_next) {
+                (Some(a), Some(b)) => a.min(b).clone(),
+                (Some(a), None) => a.clone(),
+                (None, Some(b)) => b.clone(),
+                (None, None) => break,
+            };
+            if self_next == Some(&point) {
+                if in_self {
+                    i += 1;
+                }
+                in_self = !in_self;
+            }
+            if other_next == Some(&point) {
+                if in_other {
+                    j += 1;
+                }
+                in_other = !in_other;
+            }
+            match (start.take(), keep(in_self, in_other)) {
+                (None, true) => start = Some(point),
+                (Some(range_start), true) => start = Some(range_start),
+                (Some(range_start), false) => ranges.push(range_start..point),
+                (None, false) => (),
+            }
+        }
+        Self::from_normalized(ranges)
+    }
+
+    /// The items in `self` or `other`.  The time taken is proportional to the number of ranges.
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// The items in both `self` and `other`.  The time taken is proportional to the number of
+    /// ranges.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// The items in `self` but not in `other`.  The time taken is proportional to the number
+    /// of ranges.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    /// The items in `self` or `other` but not in both.  The time taken is proportional to the
+    /// number of ranges.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a != b)
+    }
+}
+
+impl<T: Discrete> Default for OrdRangeSet<T> {
+    fn default() -> Self {
+        Self::empty_set()
+    }
+}
+
+impl<T: PartialEq> PartialEq for OrdRangeSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ranges == other.ranges
+    }
+}
+
+impl<T: Eq> Eq for OrdRangeSet<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for OrdRangeSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.ranges.iter()).finish()
+    }
+}
+
+impl<T: Discrete> FromIterator<Range<T>> for OrdRangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        Self::from_normalized(Self::normalized(iter.into_iter().collect()))
+    }
+}
+
+impl<T: Discrete> From<Range<T>> for OrdRangeSet<T> {
+    fn from(range: Range<T>) -> Self {
+        Self::from_iter([range])
+    }
+}
+
+impl<T: Discrete, const N: usize> From<[Range<T>; N]> for OrdRangeSet<T> {
+    /// Create an OrdRangeSet<T> from (possibly overlapping and unsorted) ranges.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_range_set::OrdRangeSet;
+    ///
+    /// let set = OrdRangeSet::<u32>::from([8..9, 0..3, 2..5, 5..6, 7..7]);
+    /// assert_eq!(set.ranges(), [0..6, 8..9]);
+    /// ```
+    fn from(ranges: [Range<T>; N]) -> Self {
+        Self::from_iter(ranges)
+    }
+}
+
+impl<T: Discrete> TryFrom<&OrdListSet<T>> for OrdRangeSet<T> {
+    type Error = MaximumValueError;
+
+    /// Create an OrdRangeSet<T> from the runs of consecutive items in an OrdListSet<T>.  This
+    /// fails if the list contains the maximum value of `T`.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_range_set::{MaximumValueError, OrdRangeSet};
+    ///
+    /// let list = OrdListSet::<u32>::from([1, 2, 3, 5, 7, 8]);
+    /// let set = OrdRangeSet::try_from(&list).unwrap();
+    /// assert_eq!(set.ranges(), [1..4, 5..6, 7..9]);
+    /// assert_eq!(OrdListSet::from(&set), list);
+    /// let list = OrdListSet::<u8>::from([1, 255]);
+    /// assert_eq!(OrdRangeSet::try_from(&list), Err(MaximumValueError));
+    /// ```
+    fn try_from(list: &OrdListSet<T>) -> Result<Self, Self::Error> {
+        let mut ranges: Vec<Range<T>> = vec![];
+        for item in list.iter() {
+            let next = item.forward(1).ok_or(MaximumValueError)?;
+            match ranges.last_mut() {
+                Some(last) if last.end == *item => last.end = next,
+                _ => ranges.push(item.clone()..next),
+            }
+        }
+        Ok(Self::from_normalized(ranges))
+    }
+}
+
+impl<T: Discrete> From<&OrdRangeSet<T>> for OrdListSet<T> {
+    fn from(set: &OrdRangeSet<T>) -> Self {
+        set.values().collect()
+    }
+}
+
+impl<T: Discrete> Sub<&OrdRangeSet<T>> for &OrdRangeSet<T> {
+    type Output = OrdRangeSet<T>;
+
+    /// Returns the difference of `self` and `rhs` as a new `OrdRangeSet<T>`.
+    fn sub(self, rhs: &OrdRangeSet<T>) -> OrdRangeSet<T> {
+        self.difference(rhs)
+    }
+}
+
+impl<T: Discrete> BitAnd<&OrdRangeSet<T>> for &OrdRangeSet<T> {
+    type Output = OrdRangeSet<T>;
+
+    /// Returns the intersection of `self` and `rhs` as a new `OrdRangeSet<T>`.
+    fn bitand(self, rhs: &OrdRangeSet<T>) -> OrdRangeSet<T> {
+        self.intersection(rhs)
+    }
+}
+
+impl<T: Discrete> BitXor<&OrdRangeSet<T>> for &OrdRangeSet<T> {
+    type Output = OrdRangeSet<T>;
+
+    /// Returns the symmetric difference of `self` and `rhs` as a new `OrdRangeSet<T>`.
+    fn bitxor(self, rhs: &OrdRangeSet<T>) -> OrdRangeSet<T> {
+        self.symmetric_difference(rhs)
+    }
+}
+
+impl<T: Discrete> BitOr<&OrdRangeSet<T>> for &OrdRangeSet<T> {
+    type Output = OrdRangeSet<T>;
+
+    /// Returns the union of `self` and `rhs` as a new `OrdRangeSet<T>`.
+    fn bitor(self, rhs: &OrdRangeSet<T>) -> OrdRangeSet<T> {
+        self.union(rhs)
+    }
+}
+
+/// An iterator over the items of an `OrdRangeSet` that expands its ranges lazily.  Seeks are
+/// O(log(n)) in the number of ranges.
+pub struct OrdRangeSetIter<'a, T> {
+    set: &'a OrdRangeSet<T>,
+    arena: &'a ValueArena<T>,
+    // The current range and the position within it
+    index: usize,
+    offset: u128,
+    // The current item (once it has been stored in the arena)
+    item: Option<&'a T>,
+}
+
+impl<'a, T> Clone for OrdRangeSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            arena: self.arena,
+            index: self.index,
+            offset: self.offset,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a, T: Discrete> OrdRangeSetIter<'a, T> {
+    // The current item (if any).
+    fn current(&self) -> Option<T> {
+        let range = self.set.ranges.get(self.index)?;
+        Some(range.start.forward(self.offset).expect("within range"))
+    }
+
+    /// The number of items remaining.
+    fn remaining(&self) -> u128 {
+        match self.set.offsets.get(self.index) {
+            Some(offset) => self.set.len - offset - self.offset,
+            None => 0,
+        }
+    }
+}
+
+impl<'a, T: Discrete> Iterator for OrdRangeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        let range = &self.set.ranges[self.index];
+        self.offset += 1;
+        if self.offset == range.start.distance_to(&range.end) {
+            self.index += 1;
+            self.offset = 0;
+        }
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining()) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+impl<'a, T: 'a + Discrete> PeepAdvanceIter<'a, T> for OrdRangeSetIter<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        if self.item.is_none() {
+            let item = self.current()?;
+            self.item = Some(self.arena.alloc(item));
+        }
+        self.item
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(
+                f,
+                "OrdRangeSetIter[range={}, remaining={}]",
+                self.index,
+                self.remaining()
+            )
+        } else {
+            write!(f, "OrdRangeSetIter[ranges={}]", self.set.ranges.len())
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Advance this iterator to the next item at or after the given item.
+    ///
+    /// Example
+    /// ```
+    /// use ord_range_set::OrdRangeSet;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    ///
+    /// let set = OrdRangeSet::<u32>::from([0..10, 20..30]);
+    /// let arena = ValueArena::default();
+    /// let mut iter = set.iter_in(&arena);
+    /// iter.advance_until(&5);
+    /// assert_eq!(iter.next(), Some(&5));
+    /// iter.advance_until(&12);
+    /// assert_eq!(iter.next(), Some(&20));
+    /// iter.advance_until(&3);
+    /// assert_eq!(iter.next(), Some(&21));
+    /// iter.advance_until(&30);
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    fn advance_until(&mut self, target: &T) {
+        // Make sure we don't go backwards
+        if let Some(item) = self.current() {
+            if item < *target {
+                let remaining = &self.set.ranges[self.index..];
+                self.index += remaining.partition_point(|range| range.end <= *target);
+                self.offset = match self.set.ranges.get(self.index) {
+                    Some(range) if range.start < *target => range.start.distance_to(target),
+                    _ => 0,
+                };
+                self.item = None;
+            }
+        }
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        match target.forward(1) {
+            Some(next) => self.advance_until(&next),
+            None => {
+                self.index = self.set.ranges.len();
+                self.offset = 0;
+                self.item = None;
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a + Discrete> OrdSetIterSetOpsIterator<'a, T> for OrdRangeSetIter<'a, T> {}
+
+impl<'a, T: 'a + Discrete> fmt::Debug for OrdRangeSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T: 'a + Discrete> fmt::Display for OrdRangeSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ord_set_iter_set_ops::SeekResult;
+
+    #[test]
+    fn set_algebra() {
+        let a = OrdRangeSet::<i32>::from([-10..-5, 0..10, 20..30, 40..41]);
+        let b = OrdRangeSet::<i32>::from([-7..2, 10..20, 29..45]);
+        let a_list = OrdListSet::from(&a);
+        let b_list = OrdListSet::from(&b);
+        assert_eq!(OrdListSet::from(&(&a | &b)), &a_list | &b_list);
+        assert_eq!(OrdListSet::from(&(&a & &b)), &a_list & &b_list);
+        assert_eq!(OrdListSet::from(&(&a - &b)), &a_list - &b_list);
+        assert_eq!(OrdListSet::from(&(&a ^ &b)), &a_list ^ &b_list);
+        assert_eq!(&a | &b, OrdRangeSet::from(-10..45));
+        assert_eq!((&a ^ &a), OrdRangeSet::empty_set());
+        assert_eq!(OrdRangeSet::try_from(&(&a_list ^ &b_list)), Ok(&a ^ &b));
+        let max = OrdListSet::from([0, i32::MAX]);
+        assert_eq!(OrdRangeSet::try_from(&max), Err(MaximumValueError));
+    }
+
+    #[test]
+    fn lazy_iteration() {
+        let set = OrdRangeSet::<u64>::from([0..300, 1_000..1_000_000, u64::MAX - 2..u64::MAX - 1]);
+        let arena = ValueArena::default();
+        let mut iter = set.iter_in(&arena);
+        assert_eq!(iter.size_hint(), (999_301, Some(999_301)));
+        iter.advance_after(&298);
+        assert_eq!(iter.next(), Some(&299));
+        assert_eq!(iter.next(), Some(&1_000));
+        assert_eq!(iter.seek(&999_999), SeekResult::Found(&999_999));
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.seek(&1_000_000).item(), Some(&(u64::MAX - 2)));
+        iter.advance_after(&u64::MAX);
+        assert_eq!(iter.next(), None);
+        // only the items that were visited have been stored
+        assert_eq!(arena.len(), 4);
+        let other = OrdRangeSet::<u64>::from(250..260);
+        assert_eq!(other.iter_in(&arena).nth(9), Some(&259));
+        assert_eq!(arena.len(), 14);
+        assert_eq!(other.values().nth(9), Some(259));
+    }
+
+    #[test]
+    fn ip_addresses() {
+        let private = OrdRangeSet::from([
+            Ipv4Addr::new(10, 0, 0, 0)..Ipv4Addr::new(11, 0, 0, 0),
+            Ipv4Addr::new(192, 168, 0, 0)..Ipv4Addr::new(192, 169, 0, 0),
+        ]);
+        let assigned = OrdRangeSet::from([
+            Ipv4Addr::new(10, 0, 0, 250)..Ipv4Addr::new(10, 0, 1, 10),
+            Ipv4Addr::new(172, 16, 0, 0)..Ipv4Addr::new(172, 16, 0, 8),
+        ]);
+        assert_eq!((&private & &assigned).len(), 16);
+        assert!(private.contains(&Ipv4Addr::new(192, 168, 1, 1)));
+        assert!(!private.contains(&Ipv4Addr::new(11, 0, 0, 0)));
+        let arena = ValueArena::default();
+        let iter = assigned.iter_in(&arena).difference(private.iter_in(&arena));
+        assert_eq!(iter.count(), 8);
+    }
+}
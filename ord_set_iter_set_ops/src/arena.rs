@@ -0,0 +1,81 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Storage for items that are computed (rather than stored) by a set but must be handed out
+//! by reference (as `PeepAdvanceIter` requires).
+//!
+//! As the references outlive any single call to `next()` (they may be collected, for
+//! example) they can't point into the iterator itself.  Sets whose items are computed
+//! therefore take a `ValueArena` from the caller when creating a `PeepAdvanceIter`
+//! (`iter_in()`) so that the caller decides how long the yielded items are kept and the set
+//! itself stays immutable.  Their `values()` iterators yield owned items without an arena.
+
+use std::cell::RefCell;
+
+// The number of items allocated at a time by a `ValueArena`.
+const VALUE_CHUNK_LEN: usize = 256;
+
+/// An append only store for the items yielded by iterators over sets whose items are
+/// computed.  Iterators keep a reference to their current item so each position that an
+/// iterator (or one of its clones) visits adds (at most) one item.  Items live until the
+/// arena is cleared or dropped so an arena should be scoped to the evaluation of the
+/// expression(s) that use it.
+///
+/// # Examples
+///
+/// ```
+/// use ord_set_iter_set_ops::ValueArena;
+///
+/// let mut arena = ValueArena::default();
+/// let one = arena.alloc(1u32);
+/// let two = arena.alloc(2u32);
+/// assert_eq!((one, two), (&1, &2));
+/// assert_eq!(arena.len(), 2);
+/// arena.clear();
+/// assert!(arena.is_empty());
+/// ```
+pub struct ValueArena<T> {
+    // NB: the chunks are never grown beyond their capacity so their items never move
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Default for ValueArena<T> {
+    fn default() -> Self {
+        Self {
+            chunks: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl<T> ValueArena<T> {
+    /// Store `item` returning a reference to it that lives as long as the arena.
+    pub fn alloc(&self, item: T) -> &T {
+        let mut chunks = self.chunks.borrow_mut();
+        let full = match chunks.last() {
+            Some(chunk) => chunk.len() == chunk.capacity(),
+            None => true,
+        };
+        if full {
+            chunks.push(Vec::with_capacity(VALUE_CHUNK_LEN));
+        }
+        let chunk = chunks.last_mut().expect("just ensured");
+        chunk.push(item);
+        let item: *const T = &chunk[chunk.len() - 1];
+        // SAFETY: items are never removed while the arena is shared (`clear()` needs
+        // `&mut self`) and a chunk never reallocates (it isn't pushed to when full) so the
+        // item lives as long as `self`.
+        unsafe { &*item }
+    }
+
+    /// The number of items stored.
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discard all of the stored items.
+    pub fn clear(&mut self) {
+        self.chunks.get_mut().clear()
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! The complement of a set relative to a universe.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
+
+use super::{
+    difference_next, difference_peep, difference_size_hint, fmt_binary_expr,
+    impl_expr_debug_display, DifferenceIterator, IntersectionIterator, OrdSetIterSetOpsIterator,
+    PeepAdvanceIter, SymmetricDifferenceIterator, UnionIterator,
+};
+
+/// An iterator over the items of a universe that aren't in a set.
+///
+/// Only the complement's own `complement()` and `intersection()` (or `&` with the complement
+/// on the left) rewrite the complement as a plain difference.  Neither `complement_within()`
+/// (whose universe may differ) nor an intersection with the complement on the right (e.g.
+/// `active.intersection(not_banned)`) is rewritten as the operand types can't be inspected.
+/// Build such expressions with `SetExpr`, whose `intersection()` rewrites differences on
+/// either side.
+///
+/// NB: a complement is lazy but, when its universe computes its items (e.g. the
+/// `IntegerRange` below), every universe item that it peeps at (whether output or skipped as
+/// a member of the set) is kept in the universe's arena until the arena is cleared or
+/// dropped.  So the memory used grows with the part of the universe walked, e.g. with
+/// (almost) the whole universe for "all IDs that aren't banned".  Scope the arena to each
+/// evaluation (or use the set's `values()` and filter) when walking large universes.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use ord_set_iter_set_ops::{BTreeSetAdaptor, IntegerRange, OrdSetIterSetOpsIterator, ValueArena};
+///
+/// let user_ids = IntegerRange::<u32>::new(1..=8);
+/// let banned = BTreeSet::<u32>::from([3, 5, 6]);
+/// let active = BTreeSet::<u32>::from([1, 2, 5, 8]);
+/// let arena = ValueArena::default();
+/// let not_banned = banned.oso_iter().complement_within(user_ids.iter_in(&arena));
+/// assert_eq!(format!("{not_banned}"), "Complement(IntegerRange, BTreeSet)");
+/// let iter = not_banned.clone().intersection(active.oso_iter());
+/// assert_eq!(format!("{iter}"), "Difference(Intersection(BTreeSet, IntegerRange), BTreeSet)");
+/// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![1, 2, 8]);
+/// let iter = active.oso_iter().intersection(not_banned.clone());
+/// assert_eq!(format!("{iter}"), "Intersection(BTreeSet, Complement(IntegerRange, BTreeSet))");
+/// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![1, 2, 8]);
+/// let iter = not_banned.complement();
+/// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![3, 5, 6]);
+/// ```
+#[derive(Clone)]
+pub struct Complement<'a, T: Ord + Clone> {
+    universe: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+    set: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+}
+
+impl<'a, T: 'a + Ord + Clone> Complement<'a, T> {
+    pub fn new(
+        universe: impl PeepAdvanceIter<'a, T> + 'a,
+        set: impl PeepAdvanceIter<'a, T> + 'a,
+    ) -> Self {
+        Self {
+            universe: Box::new(universe),
+            set: Box::new(set),
+        }
+    }
+
+    /// The complement of this complement (within the same universe), i.e. the items of the
+    /// set that are in the universe.
+    pub fn complement(self) -> IntersectionIterator<'a, T> {
+        IntersectionIterator::new(self.set, self.universe)
+    }
+
+    /// The items of `other` that are in this complement, evaluated as the difference between
+    /// `other` (within the universe) and the set rather than by evaluating the complement.
+    pub fn intersection(
+        self,
+        other: impl PeepAdvanceIter<'a, T> + 'a,
+    ) -> DifferenceIterator<'a, T> {
+        DifferenceIterator::new(IntersectionIterator::new(other, self.universe), self.set)
+    }
+}
+
+impl<'a, T: Ord + Clone> Iterator for Complement<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        difference_next!(self.universe, self.set)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        difference_size_hint!(self.universe, self.set)
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Complement<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        difference_peep!(self.universe, self.set)
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Complement", self.universe, self.set, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.universe.has_fast_advance() && self.set.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        // only the universe's items matter
+        self.set.restrict_end(end);
+        self.universe.restrict_end(end)
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        self.universe.advance_until(target);
+        self.set.advance_until(target);
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        self.universe.advance_after(target);
+        self.set.advance_after(target);
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Complement<'a, T> {}
+
+impl_expr_debug_display!(Complement);
+
+#[allow(clippy::from_over_into)] // NB: we can't do From() on an imported struct
+impl<'a, T: 'a + Ord + Clone> Into<BTreeSet<T>> for Complement<'a, T> {
+    fn into(self) -> BTreeSet<T> {
+        BTreeSet::<T>::from_iter(self.cloned())
+    }
+}
+
+// NB: `&` is implemented by hand (rather than by `impl_set_op_operators!()`) so that it gets
+// the same rewrite as `intersection()`.
+impl<'a, T, I> std::ops::BitOr<I> for Complement<'a, T>
+where
+    T: 'a + Ord + Clone,
+    I: PeepAdvanceIter<'a, T> + 'a,
+{
+    type Output = UnionIterator<'a, T>;
+
+    fn bitor(self, rhs: I) -> Self::Output {
+        UnionIterator::new(self, rhs)
+    }
+}
+
+impl<'a, T, I> std::ops::BitAnd<I> for Complement<'a, T>
+where
+    T: 'a + Ord + Clone,
+    I: PeepAdvanceIter<'a, T> + 'a,
+{
+    type Output = DifferenceIterator<'a, T>;
+
+    fn bitand(self, rhs: I) -> Self::Output {
+        self.intersection(rhs)
+    }
+}
+
+impl<'a, T, I> std::ops::Sub<I> for Complement<'a, T>
+where
+    T: 'a + Ord + Clone,
+    I: PeepAdvanceIter<'a, T> + 'a,
+{
+    type Output = DifferenceIterator<'a, T>;
+
+    fn sub(self, rhs: I) -> Self::Output {
+        DifferenceIterator::new(self, rhs)
+    }
+}
+
+impl<'a, T, I> std::ops::BitXor<I> for Complement<'a, T>
+where
+    T: 'a + Ord + Clone,
+    I: PeepAdvanceIter<'a, T> + 'a,
+{
+    type Output = SymmetricDifferenceIterator<'a, T>;
+
+    fn bitxor(self, rhs: I) -> Self::Output {
+        SymmetricDifferenceIterator::new(self, rhs)
+    }
+}
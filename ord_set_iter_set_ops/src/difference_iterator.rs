@@ -2,8 +2,10 @@
 
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
 
-use super::{OrdSetIterSetOpsIterator, PeepAdvanceIter};
+use super::{fmt_binary_expr, impl_expr_debug_display, OrdSetIterSetOpsIterator, PeepAdvanceIter};
 
 #[macro_export]
 macro_rules! difference_next {
@@ -61,6 +63,18 @@ macro_rules! difference_peep {
     }};
 }
 
+#[macro_export]
+macro_rules! difference_size_hint {
+    ($left_iter: expr, $right_iter: expr) => {{
+        let (l_lower, l_upper) = $left_iter.size_hint();
+        let lower = match $right_iter.size_hint().1 {
+            Some(r_upper) => l_lower.saturating_sub(r_upper),
+            None => 0,
+        };
+        (lower, l_upper)
+    }};
+}
+
 #[derive(Clone)]
 pub struct DifferenceIterator<'a, T: Ord + Clone> {
     left_iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
@@ -85,6 +99,10 @@ impl<'a, T: Ord + Clone> Iterator for DifferenceIterator<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         difference_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        difference_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T> PeepAdvanceIter<'a, T> for DifferenceIterator<'a, T>
@@ -95,6 +113,20 @@ where
         difference_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Difference", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.left_iter.has_fast_advance() && self.right_iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        // only the left iterator's items matter
+        self.right_iter.restrict_end(end);
+        self.left_iter.restrict_end(end)
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target);
@@ -111,6 +143,8 @@ impl<'a, T: 'a + Ord + Clone + Default> OrdSetIterSetOpsIterator<'a, T>
 {
 }
 
+impl_expr_debug_display!(DifferenceIterator);
+
 #[allow(clippy::from_over_into)] // NB: we can't do From() on an imported struct
 impl<'a, T: 'a + Ord + Clone> Into<BTreeSet<T>> for DifferenceIterator<'a, T> {
     fn into(self) -> BTreeSet<T> {
@@ -0,0 +1,340 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Opt-in instrumentation of the nodes of an iterator tree.
+//!
+//! Wrapping a node in an `Instrumented` iterator (via a `StatsCollector`) counts the calls
+//! made on it and measures how far its seeks moved so that the cost of evaluating an
+//! expression can be attributed to its operands.  Item comparisons aren't observable from
+//! outside a node so only an estimate of those made by seeks is given.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::BTreeSet;
+//! use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator, StatsCollector};
+//!
+//! let set1 = BTreeSet::<u32>::from_iter(0..10);
+//! let set2 = BTreeSet::<u32>::from([2, 7, 20]);
+//! let stats = StatsCollector::default();
+//! let iter = set1
+//!     .oso_iter()
+//!     .instrumented("set1", &stats)
+//!     .intersection(set2.oso_iter().instrumented("set2", &stats))
+//!     .instrumented("set1 & set2", &stats);
+//! assert_eq!(
+//!     format!("{iter}"),
+//!     r#"Instrumented("set1 & set2", Intersection(Instrumented("set1", BTreeSet), Instrumented("set2", BTreeSet)))"#
+//! );
+//! assert_eq!(iter.count(), 2);
+//! let report = stats.report();
+//! assert_eq!(report[0].label, "set1");
+//! assert_eq!(report[0].next_calls, 2);
+//! assert_eq!(report[0].seek_distance, 8);
+//! assert_eq!(report[2].next_calls, 3);
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Bound, Index};
+use std::rc::Rc;
+
+use super::{OrdSetIterSetOpsIterator, PeepAdvanceIter, SeekResult};
+
+/// The statistics gathered for a single instrumented node.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeStats {
+    pub label: String,
+    pub next_calls: usize,
+    pub peep_calls: usize,
+    pub advance_until_calls: usize,
+    pub advance_after_calls: usize,
+    /// The total number of items passed over by seeks whose distance could be measured.
+    pub seek_distance: usize,
+    /// The longest measured seek.
+    pub max_seek_distance: usize,
+    /// The number of seeks whose distance couldn't be measured (because the node's
+    /// `size_hint()` isn't exact).
+    pub unmeasured_seeks: usize,
+    /// An estimate (not a count) of the item comparisons made within the node by its seeks:
+    /// one to decide whether the seek is needed plus either log2 of the distance (for nodes
+    /// with fast advance) or the distance.  Comparisons made by the node's parent while
+    /// merging, and those of unmeasured seeks, aren't included.
+    pub estimated_comparisons: usize,
+}
+
+impl NodeStats {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// The total number of calls made on the node.
+    pub fn total_calls(&self) -> usize {
+        self.next_calls + self.peep_calls + self.advance_until_calls + self.advance_after_calls
+    }
+
+    fn record_seek(&mut self, distance: Option<usize>, fast_advance: bool) {
+        self.estimated_comparisons += 1;
+        match distance {
+            Some(distance) => {
+                self.seek_distance += distance;
+                self.max_seek_distance = self.max_seek_distance.max(distance);
+                self.estimated_comparisons += if fast_advance {
+                    (usize::BITS - distance.leading_zeros()) as usize
+                } else {
+                    distance
+                };
+            }
+            None => self.unmeasured_seeks += 1,
+        }
+    }
+}
+
+/// The statistics for all the nodes attached to a `StatsCollector` in attachment order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StatsReport {
+    nodes: Vec<NodeStats>,
+}
+
+impl StatsReport {
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NodeStats> {
+        self.nodes.iter()
+    }
+
+    /// The statistics for the node with the given label (if any).
+    pub fn get(&self, label: &str) -> Option<&NodeStats> {
+        self.nodes.iter().find(|node| node.label == label)
+    }
+
+    /// The node on which the most calls were made (if any).
+    pub fn dominant(&self) -> Option<&NodeStats> {
+        self.nodes.iter().max_by_key(|node| node.total_calls())
+    }
+}
+
+impl Index<usize> for StatsReport {
+    type Output = NodeStats;
+
+    fn index(&self, index: usize) -> &NodeStats {
+        &self.nodes[index]
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .nodes
+            .iter()
+            .map(|node| node.label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        writeln!(
+            f,
+            "{:width$} {:>8} {:>8} {:>8} {:>8} {:>10} {:>8} {:>10}",
+            "node", "next", "peep", "until", "after", "skipped", "max skip", "est. cmp"
+        )?;
+        for node in self.nodes.iter() {
+            writeln!(
+                f,
+                "{:width$} {:>8} {:>8} {:>8} {:>8} {:>10} {:>8} {:>10}",
+                node.label,
+                node.next_calls,
+                node.peep_calls,
+                node.advance_until_calls,
+                node.advance_after_calls,
+                node.seek_distance,
+                node.max_seek_distance,
+                node.estimated_comparisons
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the statistics of the `Instrumented` nodes attached to it.
+#[derive(Debug, Default, Clone)]
+pub struct StatsCollector {
+    nodes: Rc<RefCell<Vec<NodeStats>>>,
+}
+
+impl StatsCollector {
+    /// Attach an instrumented wrapper (with the given label) to `iter`.
+    pub fn instrument<'a, T, I>(&self, label: &str, iter: I) -> Instrumented<'a, T, I>
+    where
+        T: 'a + Ord,
+        I: PeepAdvanceIter<'a, T> + Clone,
+    {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(NodeStats::new(label));
+        Instrumented {
+            iter,
+            collector: self.clone(),
+            index: nodes.len() - 1,
+            phantom: PhantomData,
+        }
+    }
+
+    /// A snapshot of the statistics collected so far.
+    pub fn report(&self) -> StatsReport {
+        StatsReport {
+            nodes: self.nodes.borrow().clone(),
+        }
+    }
+
+    /// Zero all counters (keeping the attached nodes).
+    pub fn reset(&self) {
+        for node in self.nodes.borrow_mut().iter_mut() {
+            *node = NodeStats::new(&node.label);
+        }
+    }
+}
+
+/// An iterator wrapper that records the calls made on it with its `StatsCollector`.
+/// NB: clones share their statistics with the original.
+pub struct Instrumented<'a, T, I> {
+    iter: I,
+    collector: StatsCollector,
+    index: usize,
+    phantom: PhantomData<&'a T>,
+}
+
+impl<'a, T, I: Clone> Clone for Instrumented<'a, T, I> {
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            collector: self.collector.clone(),
+            index: self.index,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, I> Instrumented<'a, T, I> {
+    fn update(&self, f: impl FnOnce(&mut NodeStats)) {
+        f(&mut self.collector.nodes.borrow_mut()[self.index])
+    }
+
+    /// The statistics collected for this node so far.
+    pub fn stats(&self) -> NodeStats {
+        self.collector.nodes.borrow()[self.index].clone()
+    }
+
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<'a, T, I: Iterator> Iterator for Instrumented<'a, T, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.update(|stats| stats.next_calls += 1);
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+fn exact_len(size_hint: (usize, Option<usize>)) -> Option<usize> {
+    match size_hint {
+        (lower, Some(upper)) if lower == upper => Some(lower),
+        _ => None,
+    }
+}
+
+impl<'a, T, I> PeepAdvanceIter<'a, T> for Instrumented<'a, T, I>
+where
+    T: 'a + Ord,
+    I: PeepAdvanceIter<'a, T> + Clone,
+{
+    fn peep(&mut self) -> Option<&'a T> {
+        self.update(|stats| stats.peep_calls += 1);
+        self.iter.peep()
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        write!(
+            f,
+            "Instrumented({:?}, ",
+            self.collector.nodes.borrow()[self.index].label
+        )?;
+        self.iter.fmt_expr(f, detailed)?;
+        write!(f, ")")
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        self.iter.restrict_end(end)
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        self.seek(target);
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        let before = exact_len(self.iter.size_hint());
+        self.iter.advance_after(target);
+        let distance = before.zip(exact_len(self.iter.size_hint()));
+        let fast_advance = self.iter.has_fast_advance();
+        self.update(|stats| {
+            stats.advance_after_calls += 1;
+            stats.record_seek(distance.map(|(b, a)| b.saturating_sub(a)), fast_advance);
+        });
+    }
+
+    // NB: seeks are recorded as `advance_until()` calls
+    fn seek(&mut self, target: &T) -> SeekResult<&'a T> {
+        let before = exact_len(self.iter.size_hint());
+        let result = self.iter.seek(target);
+        let distance = before.zip(exact_len(self.iter.size_hint()));
+        let fast_advance = self.iter.has_fast_advance();
+        self.update(|stats| {
+            stats.advance_until_calls += 1;
+            stats.record_seek(distance.map(|(b, a)| b.saturating_sub(a)), fast_advance);
+        });
+        result
+    }
+}
+
+impl<'a, T, I> OrdSetIterSetOpsIterator<'a, T> for Instrumented<'a, T, I>
+where
+    T: 'a + Ord + Clone,
+    I: PeepAdvanceIter<'a, T> + Clone,
+{
+}
+
+impl<'a, T, I> fmt::Debug for Instrumented<'a, T, I>
+where
+    T: 'a + Ord,
+    I: PeepAdvanceIter<'a, T> + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T, I> fmt::Display for Instrumented<'a, T, I>
+where
+    T: 'a + Ord,
+    I: PeepAdvanceIter<'a, T> + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
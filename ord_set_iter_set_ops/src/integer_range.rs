@@ -0,0 +1,216 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Ranges of primitive integers (e.g. for use as the universe of a `Complement`).
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use super::{fmt_leaf, OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+/// The primitive integer types (of up to 64 bits) that can be the items of an `IntegerRange`.
+pub trait Integer: Copy + Ord + 'static {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn to_i128(self) -> i128;
+
+    fn from_i128(value: i128) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($int:ty),+) => {
+        $(
+            impl Integer for $int {
+                const MIN: Self = <$int>::MIN;
+                const MAX: Self = <$int>::MAX;
+
+                fn to_i128(self) -> i128 {
+                    self as i128
+                }
+
+                fn from_i128(value: i128) -> Self {
+                    value as $int
+                }
+            }
+        )+
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// A range of integers.  As iterators over sets yield references, the integers that are
+/// iterated over (as opposed to seeked past) by `iter_in()` are stored in an arena supplied
+/// by the caller.  `values()` yields the integers themselves.
+///
+/// NB: the arena only grows (by one integer per position visited) so an iterator that walks
+/// `n` integers of the range holds `n` integers in the arena (about `n * size_of::<T>()`
+/// bytes) until the arena is cleared or dropped.  This includes the positions peeped at but
+/// skipped by expressions such as `Complement`, so prefer `values()` for long walks whose
+/// items needn't be borrowed.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use ord_set_iter_set_ops::{BTreeSetAdaptor, IntegerRange, OrdSetIterSetOpsIterator, ValueArena};
+///
+/// let ids = IntegerRange::<u32>::new(1..=10);
+/// let banned = BTreeSet::<u32>::from([2, 3, 7, 11]);
+/// let arena = ValueArena::default();
+/// let allowed = banned.oso_iter().complement_within(ids.iter_in(&arena));
+/// assert_eq!(allowed.cloned().collect::<Vec<_>>(), vec![1, 4, 5, 6, 8, 9, 10]);
+/// assert_eq!(ids.values().rev().take(2).collect::<Vec<_>>(), vec![10, 9]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegerRange<T> {
+    start: i128,
+    end: i128,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Integer> IntegerRange<T> {
+    pub fn new(range: impl RangeBounds<T>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(start) => start.to_i128(),
+            Bound::Excluded(start) => start.to_i128() + 1,
+            Bound::Unbounded => T::MIN.to_i128(),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.to_i128() + 1,
+            Bound::Excluded(end) => end.to_i128(),
+            Bound::Unbounded => T::MAX.to_i128() + 1,
+        };
+        Self {
+            start,
+            end: end.max(start),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The number of integers in the range.
+    pub fn len(&self) -> u128 {
+        (self.end - self.start) as u128
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        (self.start..self.end).contains(&value.to_i128())
+    }
+
+    /// An iterator over the integers in the range that stores the integers it yields in
+    /// `arena`.
+    pub fn iter_in<'a>(&self, arena: &'a ValueArena<T>) -> IntegerRangeIter<'a, T> {
+        IntegerRangeIter {
+            arena,
+            next: self.start,
+            end: self.end,
+            item: None,
+        }
+    }
+
+    /// The integers in the range (in ascending order).
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = T> + Clone {
+        (self.start..self.end).map(T::from_i128)
+    }
+}
+
+/// An iterator over an `IntegerRange`.  Seeks are O(1).
+pub struct IntegerRangeIter<'a, T> {
+    arena: &'a ValueArena<T>,
+    next: i128,
+    end: i128,
+    // The next integer (once it has been stored in the arena)
+    item: Option<&'a T>,
+}
+
+impl<'a, T> Clone for IntegerRangeIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            arena: self.arena,
+            next: self.next,
+            end: self.end,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a, T: Integer> Iterator for IntegerRangeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        self.next += 1;
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from((self.end - self.next).max(0)) {
+            Ok(len) => (len, Some(len)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+impl<'a, T: Integer> PeepAdvanceIter<'a, T> for IntegerRangeIter<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        if self.next < self.end {
+            let (arena, next) = (self.arena, self.next);
+            Some(
+                *self
+                    .item
+                    .get_or_insert_with(|| arena.alloc(T::from_i128(next))),
+            )
+        } else {
+            None
+        }
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "IntegerRange", detailed, self.size_hint())
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        match end {
+            Bound::Included(end) => self.end = self.end.min(end.to_i128() + 1),
+            Bound::Excluded(end) => self.end = self.end.min(end.to_i128()),
+            Bound::Unbounded => (),
+        }
+        true
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        if target.to_i128() > self.next {
+            self.next = target.to_i128();
+            self.item = None;
+        }
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        if target.to_i128() >= self.next {
+            self.next = target.to_i128() + 1;
+            self.item = None;
+        }
+    }
+}
+
+impl<'a, T: Integer> OrdSetIterSetOpsIterator<'a, T> for IntegerRangeIter<'a, T> {}
+
+impl<'a, T: Integer> fmt::Debug for IntegerRangeIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, T: Integer> fmt::Display for IntegerRangeIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
@@ -2,8 +2,10 @@
 
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
 
-use super::{OrdSetIterSetOpsIterator, PeepAdvanceIter};
+use super::{fmt_binary_expr, impl_expr_debug_display, OrdSetIterSetOpsIterator, PeepAdvanceIter};
 
 #[macro_export]
 macro_rules! intersection_next {
@@ -58,6 +60,18 @@ macro_rules! intersection_peep {
     }};
 }
 
+#[macro_export]
+macro_rules! intersection_size_hint {
+    ($left_iter: expr, $right_iter: expr) => {{
+        let upper = match ($left_iter.size_hint().1, $right_iter.size_hint().1) {
+            (Some(l_upper), Some(r_upper)) => Some(l_upper.min(r_upper)),
+            (Some(l_upper), None) => Some(l_upper),
+            (None, r_upper) => r_upper,
+        };
+        (0, upper)
+    }};
+}
+
 #[derive(Clone)]
 pub struct IntersectionIterator<'a, T: Ord + Clone> {
     left_iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
@@ -82,6 +96,10 @@ impl<'a, T: Ord + Clone> Iterator for IntersectionIterator<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         intersection_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        intersection_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T> PeepAdvanceIter<'a, T> for IntersectionIterator<'a, T>
@@ -92,6 +110,20 @@ where
         intersection_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Intersection", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.left_iter.has_fast_advance() && self.right_iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l || r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target);
@@ -108,6 +140,8 @@ impl<'a, T: 'a + Ord + Clone + Default> OrdSetIterSetOpsIterator<'a, T>
 {
 }
 
+impl_expr_debug_display!(IntersectionIterator);
+
 #[allow(clippy::from_over_into)] // NB: we can't do From() on an imported struct
 impl<'a, T: 'a + Ord + Clone> Into<BTreeSet<T>> for IntersectionIterator<'a, T> {
     fn into(self) -> BTreeSet<T> {
@@ -3,17 +3,36 @@ use dyn_clonable::*;
 
 use std::cmp::Ordering;
 use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
+use std::fmt;
 use std::iter::Peekable;
+use std::ops::{Bound, RangeBounds};
 
+pub mod arena;
+pub mod complement;
 pub mod difference_iterator;
+pub mod instrumented;
+pub mod integer_range;
 pub mod intersection_iterator;
+mod operators;
+pub mod pagination;
+pub mod planner;
+pub mod restricted;
 pub mod set_relationships;
 pub mod symmetric_difference_iterator;
+pub mod tee;
 pub mod union_iterator;
 
+pub use arena::*;
+pub use complement::*;
 pub use difference_iterator::*;
+pub use instrumented::*;
+pub use integer_range::*;
 pub use intersection_iterator::*;
+pub use pagination::*;
+pub use planner::*;
+pub use restricted::*;
 pub use symmetric_difference_iterator::*;
+pub use tee::*;
 pub use union_iterator::*;
 
 /// Ordered Iterator over set operations on the contents of an ordered set.
@@ -28,9 +47,35 @@ pub trait PeepAdvanceIter<'a, T: 'a + Ord>: Iterator<Item = &'a T> + 'a + Clone
         self.peep().is_none()
     }
 
+    /// Do this iterator's `advance_until()` and `advance_after()` do better than the O(n)
+    /// default implementation?  Used by the query planner when choosing a seek strategy.
+    fn has_fast_advance(&self) -> bool {
+        false
+    }
+
+    /// Write a description of the structure of the expression that this iterator evaluates,
+    /// e.g. `Union(Intersection(OrdListSetIter[len=40], BTreeSet), BTreeSet)`.  If `detailed`
+    /// is `true` the leaves also show their position and/or the number of items remaining.
+    /// Default implementation describes the iterator as a leaf using its type name.
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(
+            f,
+            &short_type_name(std::any::type_name::<Self>()),
+            detailed,
+            self.size_hint(),
+        )
+    }
+
+    /// Cut this iterator short so that it produces no items beyond `end` and return `true`, or
+    /// return `false` if it can't (cheaply) do so.  Used by `restrict()` to push the end of its
+    /// range down to the leaves of an expression.  Default implementation returns `false`.
+    fn restrict_end(&mut self, _end: Bound<&T>) -> bool {
+        false
+    }
+
     /// Advance this iterator to the next item at or after the given item.
     /// Default implementation is O(n) but custom built implementations could be as good as O(log(n)).
-    // TODO: try to make advance_until() return &mut Self
+    /// Use `seek()` to find out where the iterator landed.
     fn advance_until(&mut self, target: &T) {
         while let Some(item) = self.peep() {
             if target > item {
@@ -43,7 +88,6 @@ pub trait PeepAdvanceIter<'a, T: 'a + Ord>: Iterator<Item = &'a T> + 'a + Clone
 
     /// Advance this iterator to the next item after the given item.
     /// Default implementation is O(n) but custom built implementations could be as good as O(log(n)).
-    // TODO: try to make advance_until() return &mut Self
     fn advance_after(&mut self, target: &T) {
         while let Some(item) = self.peep() {
             if target >= item {
@@ -53,6 +97,162 @@ pub trait PeepAdvanceIter<'a, T: 'a + Ord>: Iterator<Item = &'a T> + 'a + Clone
             }
         }
     }
+
+    /// Advance this iterator to the next item at or after the given item and report whether
+    /// the target itself was present (and is now the next item), where the iterator landed if
+    /// it wasn't or that the iterator is exhausted.
+    /// Default implementation is `advance_until()` followed by `peep()`.
+    ///
+    /// Example
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, PeepAdvanceIter, SeekResult};
+    ///
+    /// let set = BTreeSet::<u32>::from([1, 3, 5]);
+    /// let mut iter = set.oso_iter();
+    /// assert_eq!(iter.seek(&3), SeekResult::Found(&3));
+    /// assert_eq!(iter.seek(&4), SeekResult::Landed(&5));
+    /// assert_eq!(iter.seek(&6), SeekResult::Exhausted);
+    /// ```
+    fn seek(&mut self, target: &T) -> SeekResult<&'a T> {
+        self.advance_until(target);
+        SeekResult::classify(target, self.peep())
+    }
+}
+
+/// The outcome of a `PeepAdvanceIter::seek()`.  In all cases the iterator is left positioned
+/// at the reported item (if any).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekResult<R> {
+    /// The target is present.
+    Found(R),
+    /// The target is absent and this is the first item after it.
+    Landed(R),
+    /// There are no items at or after the target.
+    Exhausted,
+}
+
+impl<R> SeekResult<R> {
+    pub fn is_found(&self) -> bool {
+        matches!(self, SeekResult::Found(_))
+    }
+
+    /// The item that the iterator is positioned at (if any).
+    pub fn item(self) -> Option<R> {
+        match self {
+            SeekResult::Found(item) | SeekResult::Landed(item) => Some(item),
+            SeekResult::Exhausted => None,
+        }
+    }
+}
+
+impl<'a, T: Ord> SeekResult<&'a T> {
+    /// Classify the item found by a seek for `target`.
+    pub fn classify(target: &T, item: Option<&'a T>) -> Self {
+        match item {
+            Some(item) if item == target => SeekResult::Found(item),
+            Some(item) => SeekResult::Landed(item),
+            None => SeekResult::Exhausted,
+        }
+    }
+}
+
+// Lets the (boxed) operands of a combinator be handed on to a new combinator.
+impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Box<dyn PeepAdvanceIter<'a, T> + 'a> {
+    fn peep(&mut self) -> Option<&'a T> {
+        (**self).peep()
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        (**self).has_fast_advance()
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        (**self).fmt_expr(f, detailed)
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        (**self).restrict_end(end)
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        (**self).advance_until(target)
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        (**self).advance_after(target)
+    }
+
+    fn seek(&mut self, target: &T) -> SeekResult<&'a T> {
+        (**self).seek(target)
+    }
+}
+
+/// Strip the module paths (and elided lifetimes) from a type name.
+pub(crate) fn short_type_name(type_name: &str) -> String {
+    let mut name = String::new();
+    let mut segment = String::new();
+    for c in type_name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            name.push_str(segment.rsplit("::").next().unwrap_or(""));
+            segment.clear();
+            name.push(c);
+        }
+    }
+    name.push_str(segment.rsplit("::").next().unwrap_or(""));
+    // lifetimes are just noise
+    name.replace("'_, ", "").replace("<'_>", "")
+}
+
+// Describe a leaf iterator (with the number of items remaining if `detailed`).
+fn fmt_leaf(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    detailed: bool,
+    size_hint: (usize, Option<usize>),
+) -> fmt::Result {
+    write!(f, "{name}")?;
+    if detailed {
+        match size_hint {
+            (lower, Some(upper)) if lower == upper => write!(f, "[remaining={lower}]")?,
+            (lower, Some(upper)) => write!(f, "[remaining={lower}..={upper}]")?,
+            (lower, None) => write!(f, "[remaining={lower}..]")?,
+        }
+    }
+    Ok(())
+}
+
+/// Write the description of a binary set operation expression, e.g. `Union(left, right)`.
+#[macro_export]
+macro_rules! fmt_binary_expr {
+    ($f: expr, $name: expr, $left_iter: expr, $right_iter: expr, $detailed: expr) => {{
+        write!($f, "{}(", $name)?;
+        $left_iter.fmt_expr($f, $detailed)?;
+        write!($f, ", ")?;
+        $right_iter.fmt_expr($f, $detailed)?;
+        write!($f, ")")
+    }};
+}
+
+/// Implement `Debug` (detailed) and `Display` (structure only) for a set operation iterator
+/// type using its `PeepAdvanceIter::fmt_expr()`.
+#[macro_export]
+macro_rules! impl_expr_debug_display {
+    ($iter:ident) => {
+        impl<'a, T: 'a + Ord + Clone> std::fmt::Debug for $iter<'a, T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                $crate::PeepAdvanceIter::fmt_expr(self, f, true)
+            }
+        }
+
+        impl<'a, T: 'a + Ord + Clone> std::fmt::Display for $iter<'a, T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                $crate::PeepAdvanceIter::fmt_expr(self, f, false)
+            }
+        }
+    };
 }
 
 pub trait OrdSetIterSetOpsIterator<'a, T: 'a + Ord + Clone>:
@@ -116,20 +316,152 @@ pub trait OrdSetIterSetOpsIterator<'a, T: 'a + Ord + Clone>:
     fn union(self, other: impl PeepAdvanceIter<'a, T, Item = &'a T>) -> UnionIterator<'a, T> {
         UnionIterator::new(self, other)
     }
+
+    /// The items of `universe` that aren't in this iterator.  (NB: this isn't simplified when
+    /// this iterator is itself a `Complement`, see `Complement::complement()`.)
+    fn complement_within(self, universe: impl PeepAdvanceIter<'a, T> + 'a) -> Complement<'a, T> {
+        Complement::new(universe, self)
+    }
+
+    /// Attach this iterator to `collector` as an instrumented node with the given label.
+    fn instrumented(self, label: &str, collector: &StatsCollector) -> Instrumented<'a, T, Self> {
+        collector.instrument(label, self)
+    }
+
+    /// Is `item` a member of the set that this iterator (from its current position) produces?
+    /// Only the parts of the expression's operands needed to decide are visited.
+    ///
+    /// Example
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator};
+    ///
+    /// let set1 = BTreeSet::<u32>::from([1, 2, 3, 5, 8]);
+    /// let set2 = BTreeSet::<u32>::from([2, 3, 4, 8]);
+    /// let expr = || set1.oso_iter().difference(set2.oso_iter());
+    /// assert!(expr().contains(&5));
+    /// assert!(!expr().contains(&3));
+    /// assert!(!expr().contains(&4));
+    /// ```
+    fn contains(mut self, item: &T) -> bool {
+        self.seek(item).is_found()
+    }
+
+    /// The first item (from this iterator's current position) that is greater than or equal
+    /// to `item`.  Only the parts of the expression's operands needed to find it are visited.
+    ///
+    /// Example
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator};
+    ///
+    /// let set1 = BTreeSet::<u32>::from([1, 2, 3, 5, 8, 13]);
+    /// let set2 = BTreeSet::<u32>::from([2, 3, 4, 8]);
+    /// let expr = || set1.oso_iter().difference(set2.oso_iter());
+    /// assert_eq!(expr().ceiling(&2), Some(&5));
+    /// assert_eq!(expr().successor(&5), Some(&13));
+    /// assert_eq!(expr().floor(&12), Some(&5));
+    /// assert_eq!(expr().predecessor(&5), Some(&1));
+    /// assert_eq!(expr().predecessor(&1), None);
+    /// assert_eq!(expr().successor(&13), None);
+    /// ```
+    fn ceiling(mut self, item: &T) -> Option<&'a T> {
+        self.advance_until(item);
+        self.peep()
+    }
+
+    /// The first item (from this iterator's current position) that is greater than `item`.
+    fn successor(mut self, item: &T) -> Option<&'a T> {
+        self.advance_after(item);
+        self.peep()
+    }
+
+    /// The last item (from this iterator's current position) that is less than or equal to
+    /// `item`.  As iterators only move forward, this visits all the items up to `item`.
+    fn floor(mut self, item: &T) -> Option<&'a T> {
+        self.restrict_end(Bound::Included(item));
+        let mut floor = None;
+        while let Some(next) = self.peep() {
+            if next > item {
+                break;
+            }
+            floor = self.next();
+        }
+        floor
+    }
+
+    /// The last item (from this iterator's current position) that is less than `item`.  As
+    /// iterators only move forward, this visits all the items before `item`.
+    fn predecessor(mut self, item: &T) -> Option<&'a T> {
+        self.restrict_end(Bound::Excluded(item));
+        let mut predecessor = None;
+        while let Some(next) = self.peep() {
+            if next >= item {
+                break;
+            }
+            predecessor = self.next();
+        }
+        predecessor
+    }
+
+    /// Restrict this iterator to the items within `range`.
+    fn restrict(self, range: impl RangeBounds<T>) -> Restricted<'a, T> {
+        Restricted::new(self, range)
+    }
+
+    /// Take (at most) the next `page_size` items together with the key for resuming after
+    /// them (or `None` if there are no more items).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `page_size` is zero.
+    fn take_page(&mut self, page_size: usize) -> Page<'a, T> {
+        Page::take(self, page_size)
+    }
+
+    /// Position this (freshly built) iterator immediately after the page that produced `key`.
+    /// The seek propagates down to the leaves so the earlier pages aren't re-read.
+    fn resume_after(mut self, key: &ContinuationKey<T>) -> Self {
+        self.advance_after(key.last());
+        self
+    }
 }
 
 impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::Iter<'a, T>> {
     fn peep(&mut self) -> Option<&'a T> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeSet", detailed, self.size_hint())
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Peekable<btree_set::Iter<'a, T>> {}
 
+impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::Range<'a, T>> {
+    fn peep(&mut self) -> Option<&'a T> {
+        self.peek().copied()
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeSet::range", detailed, self.size_hint())
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T>
+    for Peekable<btree_set::Range<'a, T>>
+{
+}
+
 impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::Intersection<'a, T>> {
     fn peep(&mut self) -> Option<&'a T> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeSet::intersection", detailed, self.size_hint())
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T>
@@ -141,6 +473,10 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::Difference<
     fn peep(&mut self) -> Option<&'a T> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeSet::difference", detailed, self.size_hint())
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T>
@@ -152,6 +488,15 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::SymmetricDi
     fn peep(&mut self) -> Option<&'a T> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(
+            f,
+            "BTreeSet::symmetric_difference",
+            detailed,
+            self.size_hint(),
+        )
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T>
@@ -163,6 +508,10 @@ impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Peekable<btree_set::Union<'a, T
     fn peep(&mut self) -> Option<&'a T> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeSet::union", detailed, self.size_hint())
+    }
 }
 
 impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T>
@@ -176,6 +525,8 @@ where
 {
     fn oso_iter(&'a self) -> Peekable<btree_set::Iter<'a, T>>;
 
+    fn oso_range(&'a self, range: impl RangeBounds<T>) -> Peekable<btree_set::Range<'a, T>>;
+
     fn oso_difference(&'a self, other: &'a Self) -> Peekable<btree_set::Difference<'a, T>>;
 
     fn oso_intersection(&'a self, other: &'a Self) -> Peekable<btree_set::Intersection<'a, T>>;
@@ -193,6 +544,10 @@ impl<'a, T: 'a + Ord + Clone> BTreeSetAdaptor<'a, T> for BTreeSet<T> {
         self.iter().peekable()
     }
 
+    fn oso_range(&'a self, range: impl RangeBounds<T>) -> Peekable<btree_set::Range<'a, T>> {
+        self.range(range).peekable()
+    }
+
     fn oso_difference(&'a self, other: &'a Self) -> Peekable<btree_set::Difference<'a, T>> {
         self.difference(other).peekable()
     }
@@ -217,6 +572,10 @@ impl<'a, K: 'a + Ord, V> PeepAdvanceIter<'a, K> for Peekable<btree_map::Keys<'a,
     fn peep(&mut self) -> Option<&'a K> {
         self.peek().copied()
     }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_leaf(f, "BTreeMap::keys", detailed, self.size_hint())
+    }
 }
 
 impl<'a, K: 'a + Ord + Clone, V> OrdSetIterSetOpsIterator<'a, K>
@@ -321,6 +680,125 @@ mod test {
         );
     }
 
+    #[test]
+    fn operators() {
+        let set1 = BTreeSet::from(["a", "b", "c", "d", "e", "f"]);
+        let set2 = BTreeSet::from(["b", "c", "e", "g"]);
+        let set3 = BTreeSet::from(["e", "f", "g"]);
+        let set4 = BTreeSet::from(["b", "d", "h", "i", "j"]);
+
+        assert_eq!(
+            &(&(&set1 | &set3) - &(&set2 & &set4)) ^ &set3,
+            BTreeSet::from_iter(
+                ((set1.oso_iter().union(set3.oso_iter())
+                    - set2.oso_iter().intersection(set4.oso_iter()))
+                    ^ set3.oso_iter())
+                .cloned(),
+            )
+        );
+        assert_eq!(
+            (set1.oso_iter() & set2.oso_iter().union(set4.oso_iter()))
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec!["b", "c", "d", "e"]
+        );
+    }
+
+    #[test]
+    fn size_hints() {
+        let set1 = BTreeSet::from(["a", "b", "c", "d", "e", "f"]);
+        let set2 = BTreeSet::from(["b", "c", "e", "g"]);
+        let set3 = BTreeSet::<&str>::new();
+        for (left, right) in [
+            (&set1, &set2),
+            (&set2, &set1),
+            (&set1, &set3),
+            (&set3, &set2),
+        ] {
+            let union = left.oso_iter().union(right.oso_iter());
+            assert_eq!(
+                union.size_hint(),
+                (left.len().max(right.len()), Some(left.len() + right.len()))
+            );
+            assert_eq!(
+                left.oso_iter().intersection(right.oso_iter()).size_hint(),
+                (0, Some(left.len().min(right.len())))
+            );
+            assert_eq!(
+                left.oso_iter().difference(right.oso_iter()).size_hint(),
+                (left.len().saturating_sub(right.len()), Some(left.len()))
+            );
+            let iters: [Box<dyn PeepAdvanceIter<&str>>; 4] = [
+                Box::new(left.oso_iter().union(right.oso_iter())),
+                Box::new(left.oso_iter().intersection(right.oso_iter())),
+                Box::new(left.oso_iter().difference(right.oso_iter())),
+                Box::new(left.oso_iter().symmetric_difference(right.oso_iter())),
+            ];
+            for iter in iters {
+                let (lower, upper) = iter.size_hint();
+                let count = iter.count();
+                assert!(lower <= count && count <= upper.unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn expression_formatting() {
+        let set1 = BTreeSet::from(["a", "b", "c", "d", "e", "f"]);
+        let set2 = BTreeSet::from(["b", "c", "e", "g"]);
+        let map = BTreeMap::from([("b", 1), ("c", 3), ("g", 5), ("i", 6)]);
+        let stats = StatsCollector::default();
+        let mut iter = set1
+            .oso_iter()
+            .union(set1.oso_intersection(&set2))
+            .difference(map.oso_keys().instrumented("keys", &stats));
+        assert_eq!(
+            format!("{iter}"),
+            "Difference(Union(BTreeSet, BTreeSet::intersection), Instrumented(\"keys\", BTreeMap::keys))"
+        );
+        iter.next();
+        assert_eq!(
+            format!("{iter:?}"),
+            "Difference(Union(BTreeSet[remaining=5], BTreeSet::intersection[remaining=1..=4]), Instrumented(\"keys\", BTreeMap::keys[remaining=4]))"
+        );
+    }
+
+    #[test]
+    fn tee() {
+        let set1 = BTreeSet::<u32>::from_iter(0..100);
+        let mut branch1 = Tee::new(set1.oso_iter());
+        let mut branch2 = branch1.branch();
+        assert_eq!(branch1.next(), Some(&0));
+        assert_eq!(branch1.buffered(), 1);
+        branch1.advance_until(&10);
+        assert_eq!(branch1.peep(), Some(&10));
+        assert_eq!(branch1.size_hint(), (90, Some(90)));
+        assert_eq!(branch2.buffered(), 11);
+        assert_eq!(branch2.size_hint(), (100, Some(100)));
+        branch2.advance_after(&4);
+        assert_eq!(branch2.next(), Some(&5));
+        assert_eq!(branch2.buffered(), 5);
+        let mut branch3 = branch2.clone();
+        branch2.advance_until(&50);
+        assert_eq!(branch2.next(), Some(&50));
+        assert_eq!(branch1.buffered(), 45);
+        assert_eq!(branch3.next(), Some(&6));
+        assert_eq!(branch1.buffered(), 44);
+        drop(branch3);
+        assert_eq!(branch1.buffered(), 41);
+        drop(branch1);
+        assert_eq!(branch2.buffered(), 0);
+        branch2.advance_until(&80);
+        assert_eq!(branch2.next(), Some(&80));
+        assert_eq!(branch2.buffered(), 0);
+        // the slots of dropped branches are reused
+        for item in 0..100 {
+            assert_eq!(branch2.clone().contains(&item), item >= 81);
+        }
+        assert!(format!("{:?}", branch2.clone()).starts_with("Tee[branch=0,"));
+        assert_eq!(branch2.count(), 19);
+    }
+
     #[test]
     fn map() {
         let set1 = BTreeSet::from(["a", "b", "c", "d", "e", "f"]);
@@ -343,4 +821,101 @@ mod test {
             vec!["d", "h", "j"]
         );
     }
+
+    #[test]
+    fn pagination() {
+        let set1 = BTreeSet::<u32>::from_iter(0..1000);
+        let set2 = BTreeSet::<u32>::from_iter((0..1000).map(|i| i * 2));
+        let page = set1.oso_iter().intersection(set2.oso_iter()).take_page(10);
+        assert_eq!(page.items.len(), 10);
+        let key = page.next.unwrap();
+        assert_eq!(key.clone().into_inner(), 18);
+        let stats = StatsCollector::default();
+        let mut iter = set1
+            .oso_iter()
+            .instrumented("set1", &stats)
+            .intersection(set2.oso_iter().instrumented("set2", &stats))
+            .resume_after(&ContinuationKey::from_inner(18));
+        assert_eq!(stats.report()[0].advance_after_calls, 1);
+        assert_eq!(stats.report()[0].seek_distance, 19);
+        assert_eq!(stats.report()[0].next_calls, 0);
+        let page = iter.take_page(490);
+        assert_eq!(page.items.first(), Some(&&20));
+        assert_eq!(page.items.last(), Some(&&998));
+        assert!(page.next.is_none());
+        assert!(iter.take_page(10).items.is_empty());
+    }
+
+    #[test]
+    fn seek() {
+        let set1 = BTreeSet::<u32>::from_iter(0..20);
+        let set2 = BTreeSet::<u32>::from_iter((0..10).map(|i| i * 3));
+        let mut iter = set1.oso_iter().intersection(set2.oso_iter());
+        assert_eq!(iter.seek(&6), SeekResult::Found(&6));
+        assert_eq!(iter.seek(&7), SeekResult::Landed(&9));
+        assert_eq!(iter.seek(&3), SeekResult::Landed(&9));
+        assert_eq!(iter.next(), Some(&9));
+        assert_eq!(iter.seek(&19), SeekResult::Exhausted);
+        let iter = set1.oso_iter().symmetric_difference(set2.oso_iter());
+        assert!(iter.clone().contains(&4));
+        assert!(iter.clone().contains(&27));
+        assert!(!iter.clone().contains(&18));
+        assert_eq!(iter.clone().seek(&18).item(), Some(&19));
+    }
+
+    #[test]
+    fn restrict() {
+        use std::ops::Bound;
+        let set1 = BTreeSet::<u32>::from_iter(0..20);
+        let set2 = BTreeSet::<u32>::from_iter((0..10).map(|i| i * 3));
+        let mut iter = set1.oso_iter().union(set2.oso_iter()).restrict(5..10);
+        assert!(!iter.is_pushed_down());
+        assert_eq!(iter.size_hint(), (0, Some(23)));
+        assert_eq!(iter.seek(&7), SeekResult::Found(&7));
+        assert_eq!(iter.seek(&10), SeekResult::Exhausted);
+        let iter = set1
+            .oso_iter()
+            .intersection(set2.oso_iter())
+            .restrict((Bound::Excluded(3), Bound::Included(12)));
+        assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![6, 9, 12]);
+        // the inner restriction enforces the tighter end
+        let iter = set1.oso_iter().restrict(..15).restrict(..=3);
+        assert!(iter.is_pushed_down());
+        assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        let iter = set1.oso_range(3..).restrict(..=5);
+        assert_eq!(format!("{iter}"), "Restricted(BTreeSet::range)");
+        assert_eq!(iter.count(), 3);
+    }
+
+    #[test]
+    fn complement() {
+        let universe = IntegerRange::<i16>::new(-300..300);
+        let arena = ValueArena::default();
+        assert_eq!(universe.len(), 600);
+        assert_eq!(universe.iter_in(&arena).size_hint(), (600, Some(600)));
+        assert!(arena.is_empty());
+        let set = BTreeSet::<i16>::from_iter((-260..260).filter(|i| i % 10 != 0));
+        let iter = set.oso_iter().complement_within(universe.iter_in(&arena));
+        let expected: Vec<i16> = (-300..300)
+            .filter(|i| !(-260..260).contains(i) || i % 10 == 0)
+            .collect();
+        assert_eq!(iter.clone().cloned().collect::<Vec<_>>(), expected);
+        assert!(!iter.clone().contains(&-257));
+        assert!(!iter.clone().contains(&-256));
+        assert!(iter.clone().contains(&-270));
+        let iter = iter.restrict(-5..=20);
+        assert!(iter.is_pushed_down());
+        assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![0, 10, 20]);
+        let all = IntegerRange::<u8>::new(..);
+        let arena = ValueArena::default();
+        assert_eq!(all.iter_in(&arena).last(), Some(&255));
+        assert_eq!(arena.len(), 256);
+        // peeping and seeking past integers doesn't store them
+        let mut iter = all.iter_in(&arena);
+        assert_eq!(iter.peep(), Some(&0));
+        assert_eq!(iter.peep(), Some(&0));
+        assert_eq!(iter.seek(&128), SeekResult::Found(&128));
+        assert_eq!(arena.len(), 258);
+        assert!(IntegerRange::<u8>::new(100..).values().eq(100..=255));
+    }
 }
@@ -0,0 +1,145 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::collections::btree_set;
+use std::iter::Peekable;
+
+use super::{DifferenceIterator, IntersectionIterator, SymmetricDifferenceIterator, UnionIterator};
+
+/// Implement the set operators (`|`, `&`, `-` and `^`) for an ordered set iterator type so that
+/// they build lazy set operation iterators rather than new sets.
+///
+/// The first form implements the operators for a (local) iterator type with lifetime and item
+/// type parameters (e.g. `UnionIterator<'a, T>`) against any `PeepAdvanceIter` right hand side.
+/// The second form implements them for a (possibly foreign) left hand side type against a list
+/// of specific (local) right hand side types.
+#[macro_export]
+macro_rules! impl_set_op_operators {
+    ($iter:ident) => {
+        impl<'a, T, I> std::ops::BitOr<I> for $iter<'a, T>
+        where
+            T: 'a + Ord + Clone,
+            I: $crate::PeepAdvanceIter<'a, T> + 'a,
+        {
+            type Output = $crate::UnionIterator<'a, T>;
+
+            fn bitor(self, rhs: I) -> Self::Output {
+                $crate::UnionIterator::new(self, rhs)
+            }
+        }
+
+        impl<'a, T, I> std::ops::BitAnd<I> for $iter<'a, T>
+        where
+            T: 'a + Ord + Clone,
+            I: $crate::PeepAdvanceIter<'a, T> + 'a,
+        {
+            type Output = $crate::IntersectionIterator<'a, T>;
+
+            fn bitand(self, rhs: I) -> Self::Output {
+                $crate::IntersectionIterator::new(self, rhs)
+            }
+        }
+
+        impl<'a, T, I> std::ops::Sub<I> for $iter<'a, T>
+        where
+            T: 'a + Ord + Clone,
+            I: $crate::PeepAdvanceIter<'a, T> + 'a,
+        {
+            type Output = $crate::DifferenceIterator<'a, T>;
+
+            fn sub(self, rhs: I) -> Self::Output {
+                $crate::DifferenceIterator::new(self, rhs)
+            }
+        }
+
+        impl<'a, T, I> std::ops::BitXor<I> for $iter<'a, T>
+        where
+            T: 'a + Ord + Clone,
+            I: $crate::PeepAdvanceIter<'a, T> + 'a,
+        {
+            type Output = $crate::SymmetricDifferenceIterator<'a, T>;
+
+            fn bitxor(self, rhs: I) -> Self::Output {
+                $crate::SymmetricDifferenceIterator::new(self, rhs)
+            }
+        }
+    };
+    ($lhs:ty => $($rhs:ty),+) => {
+        $(
+            impl<'a, T: 'a + Ord + Clone> std::ops::BitOr<$rhs> for $lhs {
+                type Output = $crate::UnionIterator<'a, T>;
+
+                fn bitor(self, rhs: $rhs) -> Self::Output {
+                    $crate::UnionIterator::new(self, rhs)
+                }
+            }
+
+            impl<'a, T: 'a + Ord + Clone> std::ops::BitAnd<$rhs> for $lhs {
+                type Output = $crate::IntersectionIterator<'a, T>;
+
+                fn bitand(self, rhs: $rhs) -> Self::Output {
+                    $crate::IntersectionIterator::new(self, rhs)
+                }
+            }
+
+            impl<'a, T: 'a + Ord + Clone> std::ops::Sub<$rhs> for $lhs {
+                type Output = $crate::DifferenceIterator<'a, T>;
+
+                fn sub(self, rhs: $rhs) -> Self::Output {
+                    $crate::DifferenceIterator::new(self, rhs)
+                }
+            }
+
+            impl<'a, T: 'a + Ord + Clone> std::ops::BitXor<$rhs> for $lhs {
+                type Output = $crate::SymmetricDifferenceIterator<'a, T>;
+
+                fn bitxor(self, rhs: $rhs) -> Self::Output {
+                    $crate::SymmetricDifferenceIterator::new(self, rhs)
+                }
+            }
+        )+
+    };
+}
+
+impl_set_op_operators!(UnionIterator);
+impl_set_op_operators!(IntersectionIterator);
+impl_set_op_operators!(DifferenceIterator);
+impl_set_op_operators!(SymmetricDifferenceIterator);
+
+// NB: the orphan rules prevent us implementing the operators between two foreign types so
+// the BTree adaptors can only be combined (using operators) with our own iterators.
+impl_set_op_operators!(Peekable<btree_set::Iter<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
+impl_set_op_operators!(Peekable<btree_set::Range<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
+impl_set_op_operators!(Peekable<btree_set::Difference<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
+impl_set_op_operators!(Peekable<btree_set::Intersection<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
+impl_set_op_operators!(Peekable<btree_set::SymmetricDifference<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
+impl_set_op_operators!(Peekable<btree_set::Union<'a, T>> =>
+    UnionIterator<'a, T>,
+    IntersectionIterator<'a, T>,
+    DifferenceIterator<'a, T>,
+    SymmetricDifferenceIterator<'a, T>
+);
@@ -0,0 +1,70 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Resumable (cursor based) pagination of set operation results.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::BTreeSet;
+//! use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator};
+//!
+//! let set1 = BTreeSet::<u32>::from_iter(0..20);
+//! let set2 = BTreeSet::<u32>::from_iter((0..20).map(|i| i * 3));
+//! let expr = || set1.oso_iter().difference(set2.oso_iter());
+//!
+//! let page = expr().take_page(5);
+//! assert_eq!(page.items, vec![&1, &2, &4, &5, &7]);
+//! let key = page.next.unwrap();
+//! // ... later, without re-reading the first page ...
+//! let page = expr().resume_after(&key).take_page(5);
+//! assert_eq!(page.items, vec![&8, &10, &11, &13, &14]);
+//! let page = expr().resume_after(&page.next.unwrap()).take_page(5);
+//! assert_eq!(page.items, vec![&16, &17, &19]);
+//! assert!(page.next.is_none());
+//! ```
+
+use super::PeepAdvanceIter;
+
+/// An opaque key identifying where the next page of a result starts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContinuationKey<T> {
+    last: T,
+}
+
+impl<T> ContinuationKey<T> {
+    /// Reconstitute a key (e.g. one that was sent to a client) from its inner value.
+    pub fn from_inner(last: T) -> Self {
+        Self { last }
+    }
+
+    /// The key's inner value (e.g. for sending it to a client).
+    pub fn into_inner(self) -> T {
+        self.last
+    }
+
+    pub(crate) fn last(&self) -> &T {
+        &self.last
+    }
+}
+
+/// A page of items from a set operation iterator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<'a, T> {
+    pub items: Vec<&'a T>,
+    /// The key for resuming after this page or `None` if there are no more items.
+    pub next: Option<ContinuationKey<T>>,
+}
+
+impl<'a, T: 'a + Ord + Clone> Page<'a, T> {
+    pub(crate) fn take(iter: &mut impl PeepAdvanceIter<'a, T>, page_size: usize) -> Self {
+        // an empty page has no last item to resume after
+        assert!(page_size > 0, "page size must be greater than zero");
+        let items: Vec<&'a T> = iter.by_ref().take(page_size).collect();
+        let next = match items.last() {
+            Some(last) if iter.peep().is_some() => Some(ContinuationKey {
+                last: (*last).clone(),
+            }),
+            _ => None,
+        };
+        Self { items, next }
+    }
+}
@@ -0,0 +1,554 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Cost based planning of set expressions.
+//!
+//! A `SetExpr` describes a set expression over `PeepAdvanceIter` operands.  Planning it
+//! estimates the cardinality of each node (from the operands' `size_hint()`), reorders the
+//! operands of the commutative operations and chooses how each operand that will be seeked
+//! into (i.e. the non driving operands of intersections and the subtrahends of differences)
+//! should be advanced.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Bound;
+
+use super::{
+    impl_expr_debug_display, short_type_name, DifferenceIterator, IntersectionIterator,
+    OrdSetIterSetOpsIterator, PeepAdvanceIter, SeekResult, SymmetricDifferenceIterator,
+    UnionIterator,
+};
+
+/// Seeking into an operand whose estimated size is at least this many times the estimated size
+/// of the operand driving the seeks will use galloping (i.e. the operand's own logarithmic
+/// `advance_until()`) rather than a linear merge.
+pub const GALLOP_RATIO: usize = 4;
+
+/// How an operand is advanced when its parent seeks into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekStrategy {
+    /// Step through the operand one item at a time.
+    LinearMerge,
+    /// Use the operand's own (logarithmic) `advance_until()`.
+    Galloping,
+}
+
+impl fmt::Display for SeekStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekStrategy::LinearMerge => write!(f, "linear merge"),
+            SeekStrategy::Galloping => write!(f, "galloping"),
+        }
+    }
+}
+
+/// A set expression to be planned.
+#[derive(Clone)]
+pub enum SetExpr<'a, T: Ord + Clone> {
+    Leaf {
+        label: String,
+        iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+    },
+    Union(Vec<SetExpr<'a, T>>),
+    Intersection(Vec<SetExpr<'a, T>>),
+    /// The items in the first expression that are in none of the others.
+    Difference(Box<SetExpr<'a, T>>, Vec<SetExpr<'a, T>>),
+    SymmetricDifference(Vec<SetExpr<'a, T>>),
+}
+
+impl<'a, T: 'a + Ord + Clone> SetExpr<'a, T> {
+    /// A leaf expression labelled with (the short form of) the iterator's type name.
+    pub fn leaf<I: PeepAdvanceIter<'a, T> + 'a>(iter: I) -> Self {
+        Self::labelled(&short_type_name(std::any::type_name::<I>()), iter)
+    }
+
+    /// A leaf expression with the given label.
+    pub fn labelled(label: &str, iter: impl PeepAdvanceIter<'a, T> + 'a) -> Self {
+        SetExpr::Leaf {
+            label: label.to_string(),
+            iter: Box::new(iter),
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        match (self, other) {
+            (SetExpr::Union(mut lhs), SetExpr::Union(rhs)) => {
+                lhs.extend(rhs);
+                SetExpr::Union(lhs)
+            }
+            (SetExpr::Union(mut lhs), rhs) => {
+                lhs.push(rhs);
+                SetExpr::Union(lhs)
+            }
+            (lhs, SetExpr::Union(mut rhs)) => {
+                rhs.insert(0, lhs);
+                SetExpr::Union(rhs)
+            }
+            (lhs, rhs) => SetExpr::Union(vec![lhs, rhs]),
+        }
+    }
+
+    /// The intersection of the two expressions.  Differences (and hence complements) within
+    /// the intersection are rewritten as a difference of the intersection, i.e.
+    /// `a & (u - s)` becomes `(a & u) - s`, so the intersection drives the evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_set_iter_set_ops::{BTreeSetAdaptor, IntegerRange, SetExpr, ValueArena};
+    ///
+    /// let user_ids = IntegerRange::<u32>::new(..);
+    /// let arena = ValueArena::default();
+    /// let banned = BTreeSet::<u32>::from([3, 5, 6]);
+    /// let active = BTreeSet::<u32>::from([1, 2, 5, 8]);
+    /// let expr = SetExpr::labelled("banned", banned.oso_iter())
+    ///     .complement_within(SetExpr::labelled("user_ids", user_ids.iter_in(&arena)))
+    ///     .intersection(SetExpr::labelled("active", active.oso_iter()));
+    /// let plan = expr.plan();
+    /// assert!(plan.explain().starts_with("Difference (est. 4)\n  Intersection (est. 4)\n"));
+    /// assert_eq!(plan.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 8]);
+    /// ```
+    pub fn intersection(self, other: Self) -> Self {
+        let (lhs, lhs_subtrahends) = self.split_difference();
+        let (rhs, rhs_subtrahends) = other.split_difference();
+        lhs_subtrahends
+            .into_iter()
+            .chain(rhs_subtrahends)
+            .fold(lhs.flat_intersection(rhs), |expr, subtrahend| {
+                expr.difference(subtrahend)
+            })
+    }
+
+    // Split a difference into its minuend and subtrahends.
+    fn split_difference(self) -> (Self, Vec<Self>) {
+        match self {
+            SetExpr::Difference(minuend, subtrahends) => (*minuend, subtrahends),
+            expr => (expr, vec![]),
+        }
+    }
+
+    fn flat_intersection(self, other: Self) -> Self {
+        match (self, other) {
+            (SetExpr::Intersection(mut lhs), SetExpr::Intersection(rhs)) => {
+                lhs.extend(rhs);
+                SetExpr::Intersection(lhs)
+            }
+            (SetExpr::Intersection(mut lhs), rhs) => {
+                lhs.push(rhs);
+                SetExpr::Intersection(lhs)
+            }
+            (lhs, SetExpr::Intersection(mut rhs)) => {
+                rhs.insert(0, lhs);
+                SetExpr::Intersection(rhs)
+            }
+            (lhs, rhs) => SetExpr::Intersection(vec![lhs, rhs]),
+        }
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        match self {
+            SetExpr::Difference(minuend, mut subtrahends) => {
+                subtrahends.push(other);
+                SetExpr::Difference(minuend, subtrahends)
+            }
+            lhs => SetExpr::Difference(Box::new(lhs), vec![other]),
+        }
+    }
+
+    /// The items of `universe` that aren't in this expression, i.e. `universe - self`.
+    pub fn complement_within(self, universe: Self) -> Self {
+        universe.difference(self)
+    }
+
+    pub fn symmetric_difference(self, other: Self) -> Self {
+        match (self, other) {
+            (SetExpr::SymmetricDifference(mut lhs), SetExpr::SymmetricDifference(rhs)) => {
+                lhs.extend(rhs);
+                SetExpr::SymmetricDifference(lhs)
+            }
+            (SetExpr::SymmetricDifference(mut lhs), rhs) => {
+                lhs.push(rhs);
+                SetExpr::SymmetricDifference(lhs)
+            }
+            (lhs, SetExpr::SymmetricDifference(mut rhs)) => {
+                rhs.insert(0, lhs);
+                SetExpr::SymmetricDifference(rhs)
+            }
+            (lhs, rhs) => SetExpr::SymmetricDifference(vec![lhs, rhs]),
+        }
+    }
+
+    /// Plan the evaluation of this expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use ord_set_iter_set_ops::{SetExpr, BTreeSetAdaptor};
+    ///
+    /// let big = BTreeSet::<u32>::from_iter(0..100);
+    /// let small = BTreeSet::<u32>::from([3, 50, 200]);
+    /// let plan = SetExpr::labelled("big", big.oso_iter())
+    ///     .intersection(SetExpr::labelled("small", small.oso_iter()))
+    ///     .plan();
+    /// assert_eq!(
+    ///     plan.explain(),
+    ///     "Intersection (est. 3)\n  Leaf \"small\" (est. 3)\n  Leaf \"big\" (est. 100, linear merge)\n"
+    /// );
+    /// assert_eq!(plan.iter().cloned().collect::<Vec<_>>(), vec![3, 50]);
+    /// ```
+    pub fn plan(self) -> QueryPlan<'a, T> {
+        QueryPlan {
+            root: PlanNode::new(self),
+        }
+    }
+}
+
+enum PlanKind<'a, T: Ord + Clone> {
+    Leaf {
+        label: String,
+        iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+    },
+    Union(Vec<PlanNode<'a, T>>),
+    Intersection(Vec<PlanNode<'a, T>>),
+    Difference(Box<PlanNode<'a, T>>, Vec<PlanNode<'a, T>>),
+    SymmetricDifference(Vec<PlanNode<'a, T>>),
+}
+
+struct PlanNode<'a, T: Ord + Clone> {
+    kind: PlanKind<'a, T>,
+    estimate: usize,
+    fast_advance: bool,
+    strategy: Option<SeekStrategy>,
+}
+
+fn estimate_from_size_hint(size_hint: (usize, Option<usize>)) -> usize {
+    // An unknown upper bound is treated as being very big
+    size_hint.1.unwrap_or(usize::MAX)
+}
+
+impl<'a, T: 'a + Ord + Clone> PlanNode<'a, T> {
+    fn new(expr: SetExpr<'a, T>) -> Self {
+        match expr {
+            SetExpr::Leaf { label, iter } => Self {
+                estimate: estimate_from_size_hint(iter.size_hint()),
+                fast_advance: iter.has_fast_advance(),
+                kind: PlanKind::Leaf { label, iter },
+                strategy: None,
+            },
+            SetExpr::Union(operands) => {
+                let operands = Self::sorted_operands(operands);
+                let estimate = operands
+                    .iter()
+                    .fold(0usize, |acc, node| acc.saturating_add(node.estimate));
+                Self::composite(PlanKind::Union(operands), estimate)
+            }
+            SetExpr::Intersection(operands) => {
+                let mut operands = Self::sorted_operands(operands);
+                let estimate = operands.first().map(|node| node.estimate).unwrap_or(0);
+                for operand in operands.iter_mut().skip(1) {
+                    operand.choose_strategy(estimate);
+                }
+                Self::composite(PlanKind::Intersection(operands), estimate)
+            }
+            SetExpr::Difference(minuend, subtrahends) => {
+                let minuend = Self::new(*minuend);
+                let estimate = minuend.estimate;
+                // The biggest subtrahends are likely to remove the most items so put them first
+                let mut subtrahends = Self::sorted_operands(subtrahends);
+                subtrahends.reverse();
+                for subtrahend in subtrahends.iter_mut() {
+                    subtrahend.choose_strategy(estimate);
+                }
+                Self::composite(
+                    PlanKind::Difference(Box::new(minuend), subtrahends),
+                    estimate,
+                )
+            }
+            SetExpr::SymmetricDifference(operands) => {
+                let operands = Self::sorted_operands(operands);
+                let estimate = operands
+                    .iter()
+                    .fold(0usize, |acc, node| acc.saturating_add(node.estimate));
+                Self::composite(PlanKind::SymmetricDifference(operands), estimate)
+            }
+        }
+    }
+
+    fn sorted_operands(operands: Vec<SetExpr<'a, T>>) -> Vec<Self> {
+        let mut operands: Vec<Self> = operands.into_iter().map(Self::new).collect();
+        // NB: stable sort so that equal estimates retain the order given by the user
+        operands.sort_by_key(|node| node.estimate);
+        operands
+    }
+
+    fn composite(kind: PlanKind<'a, T>, estimate: usize) -> Self {
+        let fast_advance = match &kind {
+            PlanKind::Leaf { .. } => unreachable!(),
+            PlanKind::Union(operands)
+            | PlanKind::Intersection(operands)
+            | PlanKind::SymmetricDifference(operands) => {
+                operands.iter().all(|node| node.advances_fast())
+            }
+            PlanKind::Difference(minuend, subtrahends) => {
+                minuend.advances_fast() && subtrahends.iter().all(|node| node.advances_fast())
+            }
+        };
+        Self {
+            kind,
+            estimate,
+            fast_advance,
+            strategy: None,
+        }
+    }
+
+    fn choose_strategy(&mut self, driver_estimate: usize) {
+        self.strategy =
+            if self.fast_advance && self.estimate / GALLOP_RATIO >= driver_estimate.max(1) {
+                Some(SeekStrategy::Galloping)
+            } else {
+                Some(SeekStrategy::LinearMerge)
+            };
+    }
+
+    fn advances_fast(&self) -> bool {
+        self.fast_advance && self.strategy != Some(SeekStrategy::LinearMerge)
+    }
+
+    fn explain(&self, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = indent * 2)?;
+        match &self.kind {
+            PlanKind::Leaf { label, .. } => write!(f, "Leaf {label:?}")?,
+            PlanKind::Union(_) => write!(f, "Union")?,
+            PlanKind::Intersection(_) => write!(f, "Intersection")?,
+            PlanKind::Difference(_, _) => write!(f, "Difference")?,
+            PlanKind::SymmetricDifference(_) => write!(f, "SymmetricDifference")?,
+        }
+        if self.estimate == usize::MAX {
+            write!(f, " (est. unknown")?;
+        } else {
+            write!(f, " (est. {}", self.estimate)?;
+        }
+        if let Some(strategy) = self.strategy {
+            write!(f, ", {strategy}")?;
+        }
+        writeln!(f, ")")?;
+        match &self.kind {
+            PlanKind::Leaf { .. } => (),
+            PlanKind::Union(operands)
+            | PlanKind::Intersection(operands)
+            | PlanKind::SymmetricDifference(operands) => {
+                for operand in operands.iter() {
+                    operand.explain(indent + 1, f)?;
+                }
+            }
+            PlanKind::Difference(minuend, subtrahends) => {
+                minuend.explain(indent + 1, f)?;
+                for subtrahend in subtrahends.iter() {
+                    subtrahend.explain(indent + 1, f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn build(&self) -> PlannedIterator<'a, T> {
+        let iter = match &self.kind {
+            PlanKind::Leaf { iter, .. } => PlannedIterator { iter: iter.clone() },
+            PlanKind::Union(operands) => Self::fold(operands, |l, r| {
+                PlannedIterator::new(UnionIterator::new(l, r))
+            }),
+            PlanKind::Intersection(operands) => Self::fold(operands, |l, r| {
+                PlannedIterator::new(IntersectionIterator::new(l, r))
+            }),
+            PlanKind::SymmetricDifference(operands) => Self::fold(operands, |l, r| {
+                PlannedIterator::new(SymmetricDifferenceIterator::new(l, r))
+            }),
+            PlanKind::Difference(minuend, subtrahends) => {
+                let mut iter = minuend.build();
+                for subtrahend in subtrahends.iter() {
+                    iter = PlannedIterator::new(DifferenceIterator::new(iter, subtrahend.build()));
+                }
+                iter
+            }
+        };
+        if self.fast_advance && self.strategy == Some(SeekStrategy::LinearMerge) {
+            PlannedIterator::new(LinearAdvance { iter })
+        } else {
+            iter
+        }
+    }
+
+    fn fold(
+        operands: &[Self],
+        op: impl Fn(PlannedIterator<'a, T>, PlannedIterator<'a, T>) -> PlannedIterator<'a, T>,
+    ) -> PlannedIterator<'a, T> {
+        let mut operands = operands.iter();
+        if let Some(first) = operands.next() {
+            operands.fold(first.build(), |acc, node| op(acc, node.build()))
+        } else {
+            PlannedIterator::new(Empty(PhantomData))
+        }
+    }
+}
+
+/// The plan for evaluating a set expression.
+pub struct QueryPlan<'a, T: Ord + Clone> {
+    root: PlanNode<'a, T>,
+}
+
+impl<'a, T: 'a + Ord + Clone> QueryPlan<'a, T> {
+    /// The estimated (upper bound) number of items in the expression's result.
+    pub fn estimate(&self) -> usize {
+        self.root.estimate
+    }
+
+    /// A description of the chosen plan: one line per node showing its estimated cardinality
+    /// and (where it will be seeked into by its parent) its seek strategy.
+    pub fn explain(&self) -> String {
+        self.to_string()
+    }
+
+    /// An iterator over the result of the expression evaluated according to the plan.
+    pub fn iter(&self) -> PlannedIterator<'a, T> {
+        self.root.build()
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> fmt::Display for QueryPlan<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.explain(0, f)
+    }
+}
+
+/// An iterator over the result of a planned set expression.
+#[derive(Clone)]
+pub struct PlannedIterator<'a, T: Ord + Clone> {
+    iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+}
+
+impl<'a, T: 'a + Ord + Clone> PlannedIterator<'a, T> {
+    fn new(iter: impl PeepAdvanceIter<'a, T> + 'a) -> Self {
+        Self {
+            iter: Box::new(iter),
+        }
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> Iterator for PlannedIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for PlannedIterator<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        self.iter.peep()
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        self.iter.fmt_expr(f, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        self.iter.restrict_end(end)
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        self.iter.advance_until(target)
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        self.iter.advance_after(target)
+    }
+
+    fn seek(&mut self, target: &T) -> SeekResult<&'a T> {
+        self.iter.seek(target)
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for PlannedIterator<'a, T> {}
+
+impl_expr_debug_display!(PlannedIterator);
+
+// Hide the wrapped iterator's fast advance so that seeks step through it linearly.
+#[derive(Clone)]
+struct LinearAdvance<'a, T: Ord + Clone> {
+    iter: PlannedIterator<'a, T>,
+}
+
+impl<'a, T: 'a + Ord + Clone> Iterator for LinearAdvance<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for LinearAdvance<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        self.iter.peep()
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        write!(f, "LinearMerge(")?;
+        self.iter.fmt_expr(f, detailed)?;
+        write!(f, ")")
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        self.iter.restrict_end(end)
+    }
+}
+
+// The result of planning an expression with no operands.
+struct Empty<'a, T>(PhantomData<&'a T>);
+
+impl<'a, T> Clone for Empty<'a, T> {
+    fn clone(&self) -> Self {
+        Empty(PhantomData)
+    }
+}
+
+impl<'a, T: 'a + Ord> Iterator for Empty<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(0))
+    }
+}
+
+impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Empty<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        None
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, _detailed: bool) -> fmt::Result {
+        write!(f, "Empty")
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, _end: Bound<&T>) -> bool {
+        true
+    }
+}
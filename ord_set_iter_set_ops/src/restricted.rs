@@ -0,0 +1,158 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Evaluating an expression only within a window of items.
+
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use super::{
+    impl_expr_debug_display, impl_set_op_operators, OrdSetIterSetOpsIterator, PeepAdvanceIter,
+    SeekResult,
+};
+
+// Is `item` beyond the `end` bound?
+fn is_beyond<T: Ord>(item: &T, end: Bound<&T>) -> bool {
+    match end {
+        Bound::Included(end) => item > end,
+        Bound::Excluded(end) => item >= end,
+        Bound::Unbounded => false,
+    }
+}
+
+// Does the `end` bound cut off more items than the `other` end bound?
+fn is_tighter<T: Ord>(end: Bound<&T>, other: Bound<&T>) -> bool {
+    match (end, other) {
+        (Bound::Unbounded, _) => false,
+        (_, Bound::Unbounded) => true,
+        (Bound::Excluded(end), Bound::Included(other)) => end <= other,
+        (
+            Bound::Included(end) | Bound::Excluded(end),
+            Bound::Included(other) | Bound::Excluded(other),
+        ) => end < other,
+    }
+}
+
+/// An iterator over the items of an expression that are within a range.  The start of the
+/// range is reached by seeking and the end bound is pushed down to the leaves that can cut
+/// their range short (see `PeepAdvanceIter::restrict_end()`) so that it only needs to be
+/// checked here if some leaf couldn't.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator};
+///
+/// let set1 = BTreeSet::<u32>::from_iter(0..100);
+/// let set2 = BTreeSet::<u32>::from_iter((0..50).map(|i| i * 2));
+/// let iter = set1.oso_iter().difference(set2.oso_iter()).restrict(20..=30);
+/// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![21, 23, 25, 27, 29]);
+/// // or, pushing the range down to the BTreeSet from the start
+/// let iter = set1.oso_range(20..=30).difference(set2.oso_iter());
+/// assert_eq!(iter.cloned().collect::<Vec<_>>(), vec![21, 23, 25, 27, 29]);
+/// ```
+#[derive(Clone)]
+pub struct Restricted<'a, T: Ord + Clone> {
+    iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+    end: Bound<T>,
+    // Has `end` been pushed down to (i.e. is enforced by) the wrapped iterator?
+    pushed_down: bool,
+}
+
+impl<'a, T: 'a + Ord + Clone> Restricted<'a, T> {
+    pub fn new(iter: impl PeepAdvanceIter<'a, T> + 'a, range: impl RangeBounds<T>) -> Self {
+        let mut iter: Box<dyn PeepAdvanceIter<'a, T> + 'a> = Box::new(iter);
+        let pushed_down = iter.restrict_end(range.end_bound());
+        match range.start_bound() {
+            Bound::Included(start) => iter.advance_until(start),
+            Bound::Excluded(start) => iter.advance_after(start),
+            Bound::Unbounded => (),
+        }
+        Self {
+            iter,
+            end: range.end_bound().cloned(),
+            pushed_down,
+        }
+    }
+
+    /// Is the end of the range enforced by the wrapped iterator's leaves?
+    pub fn is_pushed_down(&self) -> bool {
+        self.pushed_down
+    }
+
+    fn within(&self, item: Option<&'a T>) -> Option<&'a T> {
+        item.filter(|item| self.pushed_down || !is_beyond(*item, self.end.as_ref()))
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> Iterator for Restricted<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.pushed_down {
+            self.peep()?;
+        }
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.pushed_down {
+            self.iter.size_hint()
+        } else {
+            (0, self.iter.size_hint().1)
+        }
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> PeepAdvanceIter<'a, T> for Restricted<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        let item = self.iter.peep();
+        self.within(item)
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(f, "Restricted[pushed_down={}](", self.pushed_down)?;
+        } else {
+            write!(f, "Restricted(")?;
+        }
+        self.iter.fmt_expr(f, detailed)?;
+        write!(f, ")")
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let pushed_down = self.iter.restrict_end(end);
+        if is_tighter(end, self.end.as_ref()) {
+            self.end = end.cloned();
+            self.pushed_down = pushed_down;
+        }
+        true
+    }
+
+    fn advance_until(&mut self, target: &T) {
+        self.iter.advance_until(target)
+    }
+
+    fn advance_after(&mut self, target: &T) {
+        self.iter.advance_after(target)
+    }
+
+    fn seek(&mut self, target: &T) -> SeekResult<&'a T> {
+        match self.iter.seek(target) {
+            SeekResult::Found(item) | SeekResult::Landed(item)
+                if self.within(Some(item)).is_none() =>
+            {
+                SeekResult::Exhausted
+            }
+            result => result,
+        }
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Restricted<'a, T> {}
+
+impl_set_op_operators!(Restricted);
+impl_expr_debug_display!(Restricted);
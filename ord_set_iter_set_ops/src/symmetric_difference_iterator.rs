@@ -2,8 +2,10 @@
 
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
 
-use super::{OrdSetIterSetOpsIterator, PeepAdvanceIter};
+use super::{fmt_binary_expr, impl_expr_debug_display, OrdSetIterSetOpsIterator, PeepAdvanceIter};
 
 #[macro_export]
 macro_rules! symmetric_difference_next {
@@ -61,6 +63,27 @@ macro_rules! symmetric_difference_peep {
     }};
 }
 
+#[macro_export]
+macro_rules! symmetric_difference_size_hint {
+    ($left_iter: expr, $right_iter: expr) => {{
+        let (l_lower, l_upper) = $left_iter.size_hint();
+        let (r_lower, r_upper) = $right_iter.size_hint();
+        let lower = match (l_upper, r_upper) {
+            (Some(l_upper), Some(r_upper)) => l_lower
+                .saturating_sub(r_upper)
+                .max(r_lower.saturating_sub(l_upper)),
+            (Some(l_upper), None) => r_lower.saturating_sub(l_upper),
+            (None, Some(r_upper)) => l_lower.saturating_sub(r_upper),
+            (None, None) => 0,
+        };
+        let upper = match (l_upper, r_upper) {
+            (Some(l_upper), Some(r_upper)) => l_upper.checked_add(r_upper),
+            _ => None,
+        };
+        (lower, upper)
+    }};
+}
+
 #[derive(Clone)]
 pub struct SymmetricDifferenceIterator<'a, T: Ord + Clone> {
     left_iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
@@ -85,6 +108,10 @@ impl<'a, T: Ord + Clone> Iterator for SymmetricDifferenceIterator<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         symmetric_difference_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        symmetric_difference_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T> PeepAdvanceIter<'a, T> for SymmetricDifferenceIterator<'a, T>
@@ -95,6 +122,26 @@ where
         symmetric_difference_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(
+            f,
+            "SymmetricDifference",
+            self.left_iter,
+            self.right_iter,
+            detailed
+        )
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.left_iter.has_fast_advance() && self.right_iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l && r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target);
@@ -111,6 +158,8 @@ impl<'a, T: 'a + Ord + Clone + Default> OrdSetIterSetOpsIterator<'a, T>
 {
 }
 
+impl_expr_debug_display!(SymmetricDifferenceIterator);
+
 #[allow(clippy::from_over_into)] // NB: we can't do From() on an imported struct
 impl<'a, T: 'a + Ord + Clone> Into<BTreeSet<T>> for SymmetricDifferenceIterator<'a, T> {
     fn into(self) -> BTreeSet<T> {
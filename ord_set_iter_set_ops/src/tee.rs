@@ -0,0 +1,235 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sharing one (possibly expensive) subexpression between several consumers.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+use super::{
+    impl_expr_debug_display, impl_set_op_operators, OrdSetIterSetOpsIterator, PeepAdvanceIter,
+};
+
+struct TeeBuffer<'a, T: Ord> {
+    source: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
+    // The items between the slowest and the fastest branch.
+    buffer: VecDeque<&'a T>,
+    // The (absolute) position of the first item in the buffer.
+    base: usize,
+    // The (absolute) position of each branch or `None` if the slot is free (because its
+    // branch has been dropped).  Free slots are reused by new branches.
+    positions: Vec<Option<usize>>,
+}
+
+impl<'a, T: 'a + Ord> TeeBuffer<'a, T> {
+    fn item_at(&mut self, position: usize) -> Option<&'a T> {
+        while position >= self.base + self.buffer.len() {
+            self.buffer.push_back(self.source.next()?);
+        }
+        Some(self.buffer[position - self.base])
+    }
+
+    fn live_branches(&self) -> usize {
+        self.positions.iter().filter(|p| p.is_some()).count()
+    }
+
+    fn trim(&mut self) {
+        if let Some(slowest) = self.positions.iter().flatten().min().copied() {
+            while self.base < slowest && self.buffer.pop_front().is_some() {
+                self.base += 1;
+            }
+        }
+    }
+
+    // Move the branch `id` to the first item at or after (or if `after` is true, after) the
+    // target.
+    fn advance(&mut self, id: usize, target: &T, after: bool) {
+        let before = |item: &T| if after { item <= target } else { item < target };
+        let position = self.positions[id].expect("live branch");
+        let in_buffer = self.buffer.partition_point(|item| before(item));
+        let mut position = position.max(self.base + in_buffer);
+        if position == self.base + self.buffer.len() {
+            if self.live_branches() == 1 {
+                // Nobody else needs the skipped items so let the source do the seeking
+                self.buffer.clear();
+                self.base = position;
+                if after {
+                    self.source.advance_after(target);
+                } else {
+                    self.source.advance_until(target);
+                }
+            } else {
+                // The other branches will need the skipped items so they must be buffered
+                while let Some(item) = self.source.peep() {
+                    if before(item) {
+                        self.buffer.push_back(item);
+                        self.source.next();
+                        position += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+        self.finish_advance(id, position);
+    }
+
+    fn finish_advance(&mut self, id: usize, position: usize) {
+        self.positions[id] = Some(position);
+        self.trim();
+    }
+}
+
+/// A branch of a shared iterator.  Each branch reads the shared (underlying) iterator at its
+/// own position and only the items between the slowest and the fastest branch are buffered.
+/// Cloning a branch creates a new branch at the same position.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use ord_set_iter_set_ops::{BTreeSetAdaptor, OrdSetIterSetOpsIterator, Tee};
+///
+/// let set1 = BTreeSet::<u32>::from([1, 2, 3, 4, 5, 6]);
+/// let set2 = BTreeSet::<u32>::from([5, 6, 7, 8, 9]);
+/// let a = BTreeSet::<u32>::from([2, 4, 6, 8]);
+/// let b = BTreeSet::<u32>::from([3, 6, 9]);
+/// // the (possibly expensive) `x` is only evaluated once
+/// let x = Tee::new(set1.oso_iter().union(set2.oso_iter()));
+/// let result = x.clone().difference(a.oso_iter()).union(x.intersection(b.oso_iter()));
+/// assert_eq!(result.cloned().collect::<Vec<_>>(), vec![1, 3, 5, 6, 7, 9]);
+/// ```
+pub struct Tee<'a, T: Ord> {
+    shared: Rc<RefCell<TeeBuffer<'a, T>>>,
+    id: usize,
+}
+
+impl<'a, T: 'a + Ord> Tee<'a, T> {
+    /// Share `iter` between branches.  The result is the first branch.
+    pub fn new(iter: impl PeepAdvanceIter<'a, T> + 'a) -> Self {
+        let shared = TeeBuffer {
+            source: Box::new(iter),
+            buffer: VecDeque::new(),
+            base: 0,
+            positions: vec![Some(0)],
+        };
+        Self {
+            shared: Rc::new(RefCell::new(shared)),
+            id: 0,
+        }
+    }
+
+    /// Create a new branch at the same position as this one.
+    pub fn branch(&self) -> Self {
+        let mut shared = self.shared.borrow_mut();
+        let position = shared.positions[self.id];
+        let id = match shared.positions.iter().position(Option::is_none) {
+            Some(id) => {
+                shared.positions[id] = position;
+                id
+            }
+            None => {
+                shared.positions.push(position);
+                shared.positions.len() - 1
+            }
+        };
+        Self {
+            shared: Rc::clone(&self.shared),
+            id,
+        }
+    }
+
+    /// The number of items currently buffered for the benefit of slower branches.
+    pub fn buffered(&self) -> usize {
+        self.shared.borrow().buffer.len()
+    }
+
+    fn position(&self) -> usize {
+        self.shared.borrow().positions[self.id].expect("live branch")
+    }
+}
+
+impl<'a, T: 'a + Ord> Clone for Tee<'a, T> {
+    fn clone(&self) -> Self {
+        self.branch()
+    }
+}
+
+impl<'a, T: Ord> Drop for Tee<'a, T> {
+    fn drop(&mut self) {
+        // NB: the buffer is only borrowed for the duration of a branch's method calls and
+        // the source can't own a branch of its own buffer (it exists before the buffer does)
+        // so no branch can be dropped while the buffer is borrowed
+        let mut shared = self.shared.borrow_mut();
+        shared.positions[self.id] = None;
+        shared.trim();
+    }
+}
+
+impl<'a, T: 'a + Ord> Iterator for Tee<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut shared = self.shared.borrow_mut();
+        let position = shared.positions[self.id].expect("live branch");
+        let item = shared.item_at(position)?;
+        shared.finish_advance(self.id, position + 1);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let shared = self.shared.borrow();
+        let buffered = shared.base + shared.buffer.len() - self.position();
+        let (lower, upper) = shared.source.size_hint();
+        (
+            lower.saturating_add(buffered),
+            upper.and_then(|upper| upper.checked_add(buffered)),
+        )
+    }
+}
+
+impl<'a, T: 'a + Ord> PeepAdvanceIter<'a, T> for Tee<'a, T> {
+    fn peep(&mut self) -> Option<&'a T> {
+        let mut shared = self.shared.borrow_mut();
+        let position = shared.positions[self.id].expect("live branch");
+        shared.item_at(position)
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        let shared = self.shared.borrow();
+        if detailed {
+            write!(
+                f,
+                "Tee[branch={}, pos={}, buffered={}](",
+                self.id,
+                self.position(),
+                shared.buffer.len()
+            )?;
+        } else {
+            write!(f, "Tee(")?;
+        }
+        shared.source.fmt_expr(f, detailed)?;
+        write!(f, ")")
+    }
+
+    /// Advance this branch to the next item at or after the given item.  Seeks within the
+    /// buffered window are O(log(n)).  Seeks beyond it are delegated to the shared iterator if
+    /// this is the only remaining branch and otherwise (as the other branches will need them)
+    /// the skipped items are buffered.
+    fn advance_until(&mut self, target: &T) {
+        self.shared.borrow_mut().advance(self.id, target, false);
+    }
+
+    /// Advance this branch to the next item after the given item.  Seeks within the buffered
+    /// window are O(log(n)).  Seeks beyond it are delegated to the shared iterator if this is
+    /// the only remaining branch and otherwise (as the other branches will need them) the
+    /// skipped items are buffered.
+    fn advance_after(&mut self, target: &T) {
+        self.shared.borrow_mut().advance(self.id, target, true);
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> OrdSetIterSetOpsIterator<'a, T> for Tee<'a, T> {}
+
+impl_set_op_operators!(Tee);
+impl_expr_debug_display!(Tee);
@@ -210,3 +210,10 @@ fn oso_iter_b_tree_map() {
     assert_eq!(oso_iter.next(), Some(&"d"));
     assert_eq!(oso_iter.next(), None);
 }
+
+#[test]
+#[should_panic(expected = "page size must be greater than zero")]
+fn take_empty_page() {
+    let set = BTreeSet::<u32>::from([1, 2, 3]);
+    set.oso_iter().take_page(0);
+}
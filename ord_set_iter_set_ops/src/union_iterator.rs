@@ -2,8 +2,10 @@
 
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
 
-use super::{OrdSetIterSetOpsIterator, PeepAdvanceIter};
+use super::{fmt_binary_expr, impl_expr_debug_display, OrdSetIterSetOpsIterator, PeepAdvanceIter};
 
 #[macro_export]
 macro_rules! union_next {
@@ -45,6 +47,19 @@ macro_rules! union_peep {
     }};
 }
 
+#[macro_export]
+macro_rules! union_size_hint {
+    ($left_iter: expr, $right_iter: expr) => {{
+        let (l_lower, l_upper) = $left_iter.size_hint();
+        let (r_lower, r_upper) = $right_iter.size_hint();
+        let upper = match (l_upper, r_upper) {
+            (Some(l_upper), Some(r_upper)) => l_upper.checked_add(r_upper),
+            _ => None,
+        };
+        (l_lower.max(r_lower), upper)
+    }};
+}
+
 #[derive(Clone)]
 pub struct UnionIterator<'a, T: Ord + Clone> {
     left_iter: Box<dyn PeepAdvanceIter<'a, T> + 'a>,
@@ -69,6 +84,10 @@ impl<'a, T: Ord + Clone> Iterator for UnionIterator<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         union_next!(self.left_iter, self.right_iter)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        union_size_hint!(self.left_iter, self.right_iter)
+    }
 }
 
 impl<'a, T> PeepAdvanceIter<'a, T> for UnionIterator<'a, T>
@@ -79,6 +98,20 @@ where
         union_peep!(self.left_iter, self.right_iter)
     }
 
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        fmt_binary_expr!(f, "Union", self.left_iter, self.right_iter, detailed)
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        self.left_iter.has_fast_advance() && self.right_iter.has_fast_advance()
+    }
+
+    fn restrict_end(&mut self, end: Bound<&T>) -> bool {
+        let l = self.left_iter.restrict_end(end);
+        let r = self.right_iter.restrict_end(end);
+        l && r
+    }
+
     fn advance_until(&mut self, target: &T) {
         self.left_iter.advance_until(target);
         self.right_iter.advance_until(target);
@@ -92,6 +125,8 @@ where
 
 impl<'a, T: 'a + Ord + Clone + Default> OrdSetIterSetOpsIterator<'a, T> for UnionIterator<'a, T> {}
 
+impl_expr_debug_display!(UnionIterator);
+
 #[allow(clippy::from_over_into)] // NB: we can't do From() on an imported struct
 impl<'a, T: 'a + Ord + Clone> Into<BTreeSet<T>> for UnionIterator<'a, T> {
     fn into(self) -> BTreeSet<T> {
@@ -0,0 +1,648 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets of variable length keys stored on disk as sorted string tables that can be combined
+//! with in memory sets without loading them.
+//!
+//! The file format is:
+//! - data blocks each comprising (ascending) entries of a varint key length followed by the
+//!   key's bytes,
+//! - a block index with an entry for each block comprising its first key (as per data
+//!   blocks) and the varint offset, byte length and entry count of the block, and
+//! - a footer comprising the offset and length of the block index, the number of keys (each
+//!   as a little endian `u64`) and the magic bytes `OSSTABLE`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+mod writer;
+
+pub use writer::SSTableWriter;
+
+const MAGIC: [u8; 8] = *b"OSSTABLE";
+const FOOTER_SIZE: u64 = 32;
+// The size (in bytes) at which blocks are ended by default.
+const DEFAULT_BLOCK_SIZE: usize = 4096;
+// The maximum number of keys preallocated for a block (as the index may be corrupt).
+const MAX_PREALLOCATION: usize = 4096;
+
+/// The key types that can be stored in a sorted string table.  Their order must be the
+/// (lexicographic) order of their bytes.
+pub trait SSTableKey: Ord + Clone {
+    fn key_bytes(&self) -> &[u8];
+
+    fn from_key_bytes(bytes: Vec<u8>) -> io::Result<Self>;
+}
+
+impl SSTableKey for Vec<u8> {
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_key_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        Ok(bytes)
+    }
+}
+
+impl SSTableKey for String {
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    fn from_key_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn corrupt(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt sorted string table: {what}"),
+    )
+}
+
+// Reads varints and keys from a buffer (checking that they're in bounds).
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.offset)
+                .ok_or_else(|| corrupt("truncated varint"))?;
+            self.offset += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(corrupt("overlong varint"))
+    }
+
+    fn key(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.varint()? as usize;
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or_else(|| corrupt("truncated key"))?;
+        let key = self.bytes[self.offset..end].to_vec();
+        self.offset = end;
+        Ok(key)
+    }
+}
+
+struct BlockInfo<K> {
+    first: K,
+    offset: u64,
+    len: usize,
+    // The number of keys in this block and the blocks before it
+    count: usize,
+    before: usize,
+}
+
+/// A set of keys stored in a sorted string table.  Opening the table reads only the footer
+/// and the block index: each iterator reads data blocks (one at a time) as it reaches them
+/// and the keys it yields are stored in a caller supplied arena.  As `PeepAdvanceIter` has
+/// no way to report errors, a failure to read a block ends the iterator's iteration (for
+/// good) and the error is kept for the set (see `take_error()`).
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use ord_list_set::OrdListSet;
+/// use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, ValueArena};
+/// use ord_sstable_set::{SSTableSet, SSTableWriter};
+///
+/// let mut writer = SSTableWriter::with_block_size(Cursor::new(vec![]), 256);
+/// let keys: Vec<String> = (0..10_000).map(|i| format!("user/{i:05}")).collect();
+/// writer.extend(keys.iter()).unwrap();
+/// let table = SSTableSet::<_, String>::open(writer.finish().unwrap()).unwrap();
+///
+/// let wanted = OrdListSet::<String>::from(["user/00042", "user/05000", "user/99999"].map(String::from));
+/// let arena = ValueArena::default();
+/// let found = table.iter_in(&arena).intersection(wanted.iter());
+/// assert_eq!(found.cloned().collect::<Vec<_>>(), vec!["user/00042", "user/05000"]);
+/// assert!(table.blocks_read() <= 4);
+/// assert!(table.take_error().is_none());
+/// ```
+pub struct SSTableSet<R, K> {
+    reader: Mutex<R>,
+    index: Box<[BlockInfo<K>]>,
+    len: usize,
+    blocks_read: AtomicUsize,
+    error: Mutex<Option<io::Error>>,
+}
+
+impl<K: SSTableKey> SSTableSet<BufReader<File>, K> {
+    /// Open the sorted string table in the file at `path`.
+    pub fn open_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek, K: SSTableKey> SSTableSet<R, K> {
+    /// Open the sorted string table in `reader` (reading its footer and block index).
+    pub fn open(mut reader: R) -> io::Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        if file_len < FOOTER_SIZE {
+            return Err(corrupt("too short"));
+        }
+        reader.seek(SeekFrom::Start(file_len - FOOTER_SIZE))?;
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        reader.read_exact(&mut footer)?;
+        if footer[24..] != MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        let u64_at = |start: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&footer[start..start + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let (index_offset, index_len, len) = (u64_at(0), u64_at(8), u64_at(16));
+        if index_offset.checked_add(index_len) != Some(file_len - FOOTER_SIZE) {
+            return Err(corrupt("bad index location"));
+        }
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let mut bytes = vec![0u8; index_len as usize];
+        reader.read_exact(&mut bytes)?;
+        let mut decoder = Decoder {
+            bytes: &bytes,
+            offset: 0,
+        };
+        let mut index: Vec<BlockInfo<K>> = vec![];
+        let mut before = 0;
+        while decoder.offset < bytes.len() {
+            let first = K::from_key_bytes(decoder.key()?)?;
+            let offset = decoder.varint()?;
+            let block_len = decoder.varint()? as usize;
+            let count = decoder.varint()? as usize;
+            // NB: each key takes at least one byte (for its length)
+            if index.last().is_some_and(|last| last.first >= first)
+                || offset
+                    .checked_add(block_len as u64)
+                    .filter(|end| *end <= index_offset)
+                    .is_none()
+                || count == 0
+                || count > block_len
+            {
+                return Err(corrupt("bad block index"));
+            }
+            index.push(BlockInfo {
+                first,
+                offset,
+                len: block_len,
+                count,
+                before,
+            });
+            before = before
+                .checked_add(count)
+                .ok_or_else(|| corrupt("bad block index"))?;
+        }
+        if before as u64 != len {
+            return Err(corrupt("key count mismatch"));
+        }
+        Ok(Self {
+            reader: Mutex::new(reader),
+            index: index.into_boxed_slice(),
+            len: before,
+            blocks_read: AtomicUsize::new(0),
+            error: Mutex::new(None),
+        })
+    }
+
+    /// The number of keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of data blocks.
+    pub fn block_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The number of data block reads so far (by iterators and lookups).
+    pub fn blocks_read(&self) -> usize {
+        self.blocks_read.load(Ordering::Relaxed)
+    }
+
+    /// The (first) error that ended an iterator's iteration (if any).
+    pub fn take_error(&self) -> Option<io::Error> {
+        lock(&self.error).take()
+    }
+
+    // Keep `error` (unless an earlier one hasn't been taken yet).
+    fn record_error(&self, error: io::Error) {
+        lock(&self.error).get_or_insert(error);
+    }
+
+    // Read the keys in the given block into `keys`.
+    fn read_block(&self, block: usize, keys: &mut Vec<K>) -> io::Result<()> {
+        let info = &self.index[block];
+        keys.clear();
+        let mut bytes = vec![0u8; info.len];
+        {
+            let mut reader = lock(&self.reader);
+            reader.seek(SeekFrom::Start(info.offset))?;
+            reader.read_exact(&mut bytes)?;
+        }
+        self.blocks_read.fetch_add(1, Ordering::Relaxed);
+        let mut decoder = Decoder {
+            bytes: &bytes,
+            offset: 0,
+        };
+        keys.reserve(info.count.min(MAX_PREALLOCATION));
+        for _ in 0..info.count {
+            let key = K::from_key_bytes(decoder.key()?)?;
+            if keys.last().is_some_and(|last| *last >= key) {
+                return Err(corrupt("keys out of order"));
+            }
+            keys.push(key);
+        }
+        if keys.first() != Some(&info.first) || decoder.offset != bytes.len() {
+            return Err(corrupt("block doesn't match index"));
+        }
+        Ok(())
+    }
+
+    // The index of the block that would contain `key` (if it's a member).
+    fn block_for(&self, key: &K) -> usize {
+        self.index
+            .partition_point(|info| info.first <= *key)
+            .saturating_sub(1)
+    }
+
+    /// Returns `true` if the set contains `key`.  Reads (at most) one block.
+    pub fn contains(&self, key: &K) -> io::Result<bool> {
+        if self.is_empty() {
+            return Ok(false);
+        }
+        let mut keys = vec![];
+        self.read_block(self.block_for(key), &mut keys)?;
+        Ok(keys.binary_search(key).is_ok())
+    }
+
+    /// The keys in ascending order (reading a block at a time).  Iteration ends after the
+    /// first error.
+    pub fn values(&self) -> impl Iterator<Item = io::Result<K>> + '_ {
+        let mut failed = false;
+        (0..self.index.len())
+            .map_while(move |block| {
+                if failed {
+                    return None;
+                }
+                let mut keys = vec![];
+                Some(match self.read_block(block, &mut keys) {
+                    Ok(()) => keys.into_iter().map(Ok).collect::<Vec<_>>(),
+                    Err(error) => {
+                        failed = true;
+                        vec![Err(error)]
+                    }
+                })
+            })
+            .flatten()
+    }
+
+    /// An iterator over the keys that can be combined with other ordered set iterators.  The
+    /// keys that are iterated over (as opposed to seeked past) are stored in `arena`.
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<K>) -> SSTableIter<'a, R, K> {
+        SSTableIter {
+            set: self,
+            arena,
+            block: 0,
+            index: 0,
+            keys: vec![],
+            loaded: None,
+            failed: false,
+            item: None,
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // NB: a panic while the lock was held can't leave the reader or error inconsistent
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// An iterator over the keys in an `SSTableSet`.  Seeks binary search the block index and
+/// read only the target's block.
+pub struct SSTableIter<'a, R, K> {
+    set: &'a SSTableSet<R, K>,
+    arena: &'a ValueArena<K>,
+    // The current block and the index of the next key within it
+    block: usize,
+    index: usize,
+    // The keys of the most recently read block
+    keys: Vec<K>,
+    loaded: Option<usize>,
+    // Whether reading a block has failed (which ends iteration)
+    failed: bool,
+    // The next key (once it has been stored in the arena)
+    item: Option<&'a K>,
+}
+
+impl<'a, R, K> Clone for SSTableIter<'a, R, K> {
+    fn clone(&self) -> Self {
+        // NB: the clone reads blocks for itself (if and when it needs them)
+        Self {
+            set: self.set,
+            arena: self.arena,
+            block: self.block,
+            index: self.index,
+            keys: vec![],
+            loaded: None,
+            failed: self.failed,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, K: SSTableKey> SSTableIter<'a, R, K> {
+    fn offset(&self) -> usize {
+        match self.set.index.get(self.block) {
+            Some(info) => info.before + self.index.min(info.count),
+            None => self.set.len,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        if self.failed {
+            0
+        } else {
+            self.set.len - self.offset()
+        }
+    }
+
+    // The keys in the current block (reading it if necessary) or `None` if there are no
+    // more blocks or reading fails.
+    fn keys(&mut self) -> Option<&[K]> {
+        if self.failed || self.block >= self.set.index.len() {
+            return None;
+        }
+        if self.loaded != Some(self.block) {
+            self.loaded = None;
+            if let Err(error) = self.set.read_block(self.block, &mut self.keys) {
+                self.failed = true;
+                self.set.record_error(error);
+                return None;
+            }
+            self.loaded = Some(self.block);
+        }
+        Some(&self.keys)
+    }
+
+    // The next key (if any).
+    fn current(&mut self) -> Option<&K> {
+        let index = self.index;
+        self.keys()?.get(index)
+    }
+
+    // Move to the first key in the current block not selected by `passed` (or the start of
+    // the next block).
+    fn skip_in_block(&mut self, passed: impl Fn(&K) -> bool) {
+        self.item = None;
+        let Some(keys) = self.keys() else {
+            return;
+        };
+        let index = keys.partition_point(passed);
+        if index >= keys.len() {
+            // NB: the next block's first key isn't selected by `passed`
+            self.block += 1;
+            self.index = 0;
+        } else {
+            self.index = index;
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, K: SSTableKey> Iterator for SSTableIter<'a, R, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.peep()?;
+        self.index += 1;
+        if self.index >= self.set.index[self.block].count {
+            self.block += 1;
+            self.index = 0;
+        }
+        self.item = None;
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, R: Read + Seek, K: SSTableKey> PeepAdvanceIter<'a, K> for SSTableIter<'a, R, K> {
+    fn peep(&mut self) -> Option<&'a K> {
+        if self.item.is_none() {
+            let index = self.index;
+            let key = self.keys()?.get(index)?.clone();
+            self.item = Some(self.arena.alloc(key));
+        }
+        self.item
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(
+                f,
+                "SSTableIter[pos={}, remaining={}]",
+                self.offset(),
+                self.remaining()
+            )
+        } else {
+            write!(f, "SSTableIter[len={}]", self.set.len)
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    /// Advance this iterator to the next key at or after the given key.  Implementation
+    /// binary searches the (in memory) block index and then reads (only) the target's block.
+    ///
+    /// Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    /// use ord_sstable_set::{SSTableSet, SSTableWriter};
+    ///
+    /// let keys: Vec<Vec<u8>> = (0u32..1000).map(|i| (i * 2).to_be_bytes().to_vec()).collect();
+    /// let mut writer = SSTableWriter::with_block_size(Cursor::new(vec![]), 64);
+    /// writer.extend(keys.iter()).unwrap();
+    /// let table = SSTableSet::<_, Vec<u8>>::open(writer.finish().unwrap()).unwrap();
+    /// let arena = ValueArena::default();
+    /// let mut iter = table.iter_in(&arena);
+    /// iter.advance_until(&1001u32.to_be_bytes().to_vec());
+    /// assert_eq!(iter.next(), Some(&1002u32.to_be_bytes().to_vec()));
+    /// iter.advance_until(&10u32.to_be_bytes().to_vec());
+    /// assert_eq!(iter.next(), Some(&1004u32.to_be_bytes().to_vec()));
+    /// assert_eq!(table.blocks_read(), 2);
+    /// ```
+    fn advance_until(&mut self, target: &K) {
+        // Make sure we don't go backwards
+        if let Some(key) = self.current() {
+            if key < target {
+                self.block = self.block.max(self.set.block_for(target));
+                self.skip_in_block(|key| key < target);
+            }
+        }
+    }
+
+    fn advance_after(&mut self, target: &K) {
+        // Make sure we don't go backwards
+        if let Some(key) = self.current() {
+            if key <= target {
+                self.block = self.block.max(self.set.block_for(target));
+                self.skip_in_block(|key| key <= target);
+            }
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, K: SSTableKey> OrdSetIterSetOpsIterator<'a, K> for SSTableIter<'a, R, K> {}
+
+impl<'a, R: Read + Seek, K: SSTableKey> fmt::Debug for SSTableIter<'a, R, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a, R: Read + Seek, K: SSTableKey> fmt::Display for SSTableIter<'a, R, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ord_list_set::OrdListSet;
+
+    use super::*;
+
+    fn table(keys: &[String], block_size: usize) -> SSTableSet<Cursor<Vec<u8>>, String> {
+        let mut writer = SSTableWriter::with_block_size(Cursor::new(vec![]), block_size);
+        writer.extend(keys.iter()).unwrap();
+        SSTableSet::open(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("/data/{i:04}.bin")).collect();
+        let set = table(&keys, 100);
+        fn is_sync<T: Sync>(_: &T) {}
+        is_sync(&set);
+        assert_eq!(set.len(), 1000);
+        assert!(set.block_count() > 100);
+        let arena = ValueArena::default();
+        let mut iter = set.iter_in(&arena);
+        assert!(iter.by_ref().take(500).eq(keys[..500].iter()));
+        // NB: only the current block is held by the iterator
+        assert!(iter.keys.len() < 10 && iter.keys.capacity() < 20);
+        assert!(iter.eq(keys[500..].iter()));
+        assert_eq!(set.blocks_read(), set.block_count());
+        assert!(set.values().map(Result::unwrap).eq(keys.iter().cloned()));
+        assert!(set.contains(&keys[500]).unwrap());
+        assert!(!set.contains(&"/data".to_string()).unwrap());
+        let empty = table(&[], 100);
+        assert!(empty.is_empty() && empty.iter_in(&arena).next().is_none());
+        assert!(!empty.contains(&"a".to_string()).unwrap());
+    }
+
+    #[test]
+    fn seeks_read_only_needed_blocks() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("key{i:04}")).collect();
+        let set = table(&keys, 64);
+        let wanted = OrdListSet::<String>::from_iter(
+            ["key0007", "key0500", "key0501", "key2000"].map(String::from),
+        );
+        let arena = ValueArena::default();
+        let mut iter = set.iter_in(&arena).intersection(wanted.iter());
+        assert_eq!(iter.next(), Some(&"key0007".to_string()));
+        assert_eq!(iter.count(), 2);
+        assert!(set.blocks_read() <= 4);
+        assert_eq!(arena.len(), 6);
+        let mut iter = set.iter_in(&arena);
+        iter.advance_after(&"key0998".to_string());
+        assert_eq!(format!("{iter:?}"), "SSTableIter[pos=999, remaining=1]");
+        assert_eq!(iter.next(), Some(&"key0999".to_string()));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn corruption() {
+        let keys: Vec<String> = (0..100).map(|i| format!("key{i:04}")).collect();
+        let mut writer = SSTableWriter::with_block_size(Cursor::new(vec![]), 64);
+        writer.extend(keys.iter()).unwrap();
+        let mut bytes = writer.finish().unwrap().into_inner();
+        assert!(SSTableSet::<_, String>::open(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+        // a block whose end overflows
+        let mut index = vec![];
+        write_varint(&mut index, 1);
+        index.push(b'a');
+        write_varint(&mut index, u64::MAX);
+        write_varint(&mut index, 1 << 62);
+        write_varint(&mut index, 1);
+        let mut overflow = index.clone();
+        for field in [0, index.len() as u64, 1] {
+            overflow.extend_from_slice(&field.to_le_bytes());
+        }
+        overflow.extend_from_slice(&MAGIC);
+        let error = SSTableSet::<_, String>::open(Cursor::new(overflow))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "corrupt sorted string table: bad block index"
+        );
+        // make the second key in the first block sort before the first
+        bytes[1 + 7 + 1 + 3] = b'!';
+        let set = SSTableSet::<_, String>::open(Cursor::new(bytes)).unwrap();
+        let arena = ValueArena::default();
+        let mut iter = set.iter_in(&arena);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        let error = set.take_error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "corrupt sorted string table: keys out of order"
+        );
+        // NB: the failure isn't forgotten when the error is taken
+        assert_eq!(iter.clone().next(), None);
+        assert!(set.take_error().is_none());
+        assert!(set.contains(&keys[1]).is_err());
+        let mut values = set.values();
+        assert!(values.next().unwrap().is_err() && values.next().is_none());
+        // the failed block isn't cached so later blocks are still readable
+        assert!(set.contains(&keys[99]).unwrap());
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Streaming construction of sorted string table files.
+
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+use super::{write_varint, SSTableKey, DEFAULT_BLOCK_SIZE, MAGIC};
+
+/// Writes a sorted string table from keys supplied in ascending order.  Only the current
+/// block and the block index are held in memory.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use ord_sstable_set::{SSTableSet, SSTableWriter};
+///
+/// let mut writer = SSTableWriter::new(Cursor::new(vec![]));
+/// writer.extend(["apple", "banana", "cherry"].map(String::from).iter()).unwrap();
+/// assert!(writer.push(&"avocado".to_string()).is_err());
+/// let file = writer.finish().unwrap();
+/// let set = SSTableSet::<_, String>::open(file).unwrap();
+/// assert_eq!(set.len(), 3);
+/// ```
+pub struct SSTableWriter<W: Write, K> {
+    writer: W,
+    block_size: usize,
+    block: Vec<u8>,
+    block_count: u64,
+    // The number of bytes written so far
+    offset: u64,
+    index: Vec<u8>,
+    last: Option<Vec<u8>>,
+    len: u64,
+    phantom: PhantomData<K>,
+}
+
+impl<W: Write, K: SSTableKey> SSTableWriter<W, K> {
+    pub fn new(writer: W) -> Self {
+        Self::with_block_size(writer, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// A writer that starts a new block when the current block reaches `block_size` bytes.
+    pub fn with_block_size(writer: W, block_size: usize) -> Self {
+        Self {
+            writer,
+            block_size: block_size.max(1),
+            block: vec![],
+            block_count: 0,
+            offset: 0,
+            index: vec![],
+            last: None,
+            len: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Append `key` which must be greater than the previously pushed key.
+    pub fn push(&mut self, key: &K) -> io::Result<()> {
+        let bytes = key.key_bytes();
+        if let Some(last) = &self.last {
+            if bytes <= &last[..] {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "keys must be pushed in ascending order with no duplicates",
+                ));
+            }
+        }
+        if self.block_count == 0 {
+            // the block's first key goes in the index
+            write_varint(&mut self.index, bytes.len() as u64);
+            self.index.extend_from_slice(bytes);
+        }
+        write_varint(&mut self.block, bytes.len() as u64);
+        self.block.extend_from_slice(bytes);
+        self.block_count += 1;
+        self.len += 1;
+        self.last = Some(bytes.to_vec());
+        if self.block.len() >= self.block_size {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Append the (ascending) keys in `keys`.
+    pub fn extend<'a>(&mut self, keys: impl IntoIterator<Item = &'a K>) -> io::Result<()>
+    where
+        K: 'a,
+    {
+        for key in keys {
+            self.push(key)?;
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.block_count > 0 {
+            self.writer.write_all(&self.block)?;
+            write_varint(&mut self.index, self.offset);
+            write_varint(&mut self.index, self.block.len() as u64);
+            write_varint(&mut self.index, self.block_count);
+            self.offset += self.block.len() as u64;
+            self.block.clear();
+            self.block_count = 0;
+        }
+        Ok(())
+    }
+
+    /// Write the final block, the block index and the footer and return the underlying
+    /// writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.writer.write_all(&self.index)?;
+        self.writer.write_all(&self.offset.to_le_bytes())?;
+        self.writer
+            .write_all(&(self.index.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&self.len.to_le_bytes())?;
+        self.writer.write_all(&MAGIC)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
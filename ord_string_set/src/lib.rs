@@ -0,0 +1,590 @@
+// Copyright 2023 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+//! Sets of strings stored using front coding (each string as the length of the prefix it
+//! shares with its predecessor plus the rest of the string) for sets of paths, URLs, etc.
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Bound;
+
+use ord_list_set::OrdListSet;
+use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, PeepAdvanceIter, ValueArena};
+
+// The number of strings between restart points (where the whole string is stored).
+const RESTART_INTERVAL: usize = 16;
+
+fn encode_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Decode the varint at `*offset` in `bytes` and move `*offset` past it.
+fn decode_varint(bytes: &[u8], offset: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*offset];
+        *offset += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+// Decodes consecutive entries (starting at a restart point) into a reusable buffer.
+#[derive(Clone)]
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    key: Vec<u8>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Self {
+            bytes,
+            offset,
+            key: vec![],
+        }
+    }
+
+    // Move to the restart point at `offset` (keeping the buffer).
+    fn restart(&mut self, offset: usize) {
+        self.offset = offset;
+        self.key.clear();
+    }
+
+    fn advance(&mut self) -> &[u8] {
+        let shared = decode_varint(self.bytes, &mut self.offset);
+        let suffix_len = decode_varint(self.bytes, &mut self.offset);
+        self.key.truncate(shared);
+        self.key
+            .extend_from_slice(&self.bytes[self.offset..self.offset + suffix_len]);
+        self.offset += suffix_len;
+        &self.key
+    }
+}
+
+/// An immutable set of strings stored in order using front coding.  Every `RESTART_INTERVAL`
+/// strings the whole string is stored (a restart point) and the offsets of the restart
+/// points are kept in an index so that lookups can binary search the restart points and
+/// then decode (at most) one block of strings.  Iterators decode the strings they visit
+/// into a buffer of their own.
+///
+/// # Examples
+///
+/// ```
+/// use ord_string_set::OrdStringSet;
+///
+/// let paths = OrdStringSet::from([
+///     "/usr/lib/libc.so",
+///     "/usr/bin/cat",
+///     "/usr/bin/ls",
+///     "/usr/lib/libm.so",
+///     "/etc/passwd",
+/// ]);
+/// assert_eq!(paths.len(), 5);
+/// assert!(paths.contains("/usr/bin/ls") && !paths.contains("/usr/bin"));
+/// assert_eq!(
+///     paths.values_with_prefix("/usr/lib/").collect::<Vec<_>>(),
+///     vec!["/usr/lib/libc.so", "/usr/lib/libm.so"]
+/// );
+/// ```
+pub struct OrdStringSet {
+    bytes: Box<[u8]>,
+    restarts: Box<[usize]>,
+    len: usize,
+}
+
+impl OrdStringSet {
+    pub fn empty_set() -> Self {
+        Self::from_sorted(std::iter::empty::<&str>())
+    }
+
+    // NB: `members` must be strictly increasing
+    fn from_sorted<S: AsRef<str>>(members: impl IntoIterator<Item = S>) -> Self {
+        let mut bytes = vec![];
+        let mut restarts = vec![];
+        let mut len = 0;
+        let mut previous: Vec<u8> = vec![];
+        for member in members {
+            let member = member.as_ref().as_bytes();
+            debug_assert!(len == 0 || member > &previous[..]);
+            let shared = if len % RESTART_INTERVAL == 0 {
+                restarts.push(bytes.len());
+                0
+            } else {
+                previous
+                    .iter()
+                    .zip(member.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            };
+            encode_varint(&mut bytes, shared);
+            encode_varint(&mut bytes, member.len() - shared);
+            bytes.extend_from_slice(&member[shared..]);
+            previous.clear();
+            previous.extend_from_slice(member);
+            len += 1;
+        }
+        Self {
+            bytes: bytes.into_boxed_slice(),
+            restarts: restarts.into_boxed_slice(),
+            len,
+        }
+    }
+
+    /// The number of members.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of bytes used to store the members (including the restart index).
+    pub fn encoded_size(&self) -> usize {
+        self.bytes.len() + self.restarts.len() * std::mem::size_of::<usize>()
+    }
+
+    // The whole string stored at the given restart point.
+    fn restart_key(&self, restart: usize) -> &[u8] {
+        let mut offset = self.restarts[restart];
+        let shared = decode_varint(&self.bytes, &mut offset);
+        debug_assert_eq!(shared, 0);
+        let len = decode_varint(&self.bytes, &mut offset);
+        &self.bytes[offset..offset + len]
+    }
+
+    // The index of the first member for which `before` is false (`before` must be true for
+    // a (possibly empty) prefix of the members and false for the rest).  Only the restart
+    // points examined by the binary search and one block are decoded.
+    fn partition_point(&self, before: impl Fn(&[u8]) -> bool) -> usize {
+        let (mut restart, mut end) = (0, self.restarts.len());
+        while restart < end {
+            let mid = (restart + end) / 2;
+            if before(self.restart_key(mid)) {
+                restart = mid + 1;
+            } else {
+                end = mid;
+            }
+        }
+        if restart == 0 {
+            return 0;
+        }
+        let first = (restart - 1) * RESTART_INTERVAL;
+        let mut cursor = Cursor::new(&self.bytes, self.restarts[restart - 1]);
+        for index in first..(first + RESTART_INTERVAL).min(self.len) {
+            if !before(cursor.advance()) {
+                return index;
+            }
+        }
+        (first + RESTART_INTERVAL).min(self.len)
+    }
+
+    /// Returns `true` if the set contains the specified string.
+    pub fn contains(&self, item: &str) -> bool {
+        let item = item.as_bytes();
+        self.partition_point(|key| key < item) < self.partition_point(|key| key <= item)
+    }
+
+    /// The members in ascending order.
+    pub fn values(&self) -> impl Iterator<Item = String> + '_ {
+        let mut cursor = Cursor::new(&self.bytes, 0);
+        (0..self.len).map(move |_| to_string(cursor.advance()))
+    }
+
+    /// The members that start with `prefix` in ascending order.
+    pub fn values_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        let (start, end) = self.prefix_range(prefix.as_bytes());
+        let restart = start / RESTART_INTERVAL;
+        let offset = self
+            .restarts
+            .get(restart)
+            .copied()
+            .unwrap_or(self.bytes.len());
+        let mut cursor = Cursor::new(&self.bytes, offset);
+        (restart * RESTART_INTERVAL..end).filter_map(move |index| {
+            let key = cursor.advance();
+            (index >= start).then(|| to_string(key))
+        })
+    }
+
+    /// An iterator over the members that can be combined with other ordered set iterators.
+    /// The members that are iterated over (as opposed to seeked past) are stored in `arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_set_iter_set_ops::{OrdSetIterSetOpsIterator, ValueArena};
+    /// use ord_string_set::OrdStringSet;
+    ///
+    /// let installed = OrdStringSet::from(["/bin/cat", "/bin/ls", "/bin/sh", "/sbin/init"]);
+    /// let wanted = OrdListSet::<String>::from(["/bin/ls".to_string(), "/bin/vi".to_string()]);
+    /// let arena = ValueArena::default();
+    /// let missing = wanted.iter().difference(installed.iter_in(&arena));
+    /// assert_eq!(missing.cloned().collect::<Vec<_>>(), vec!["/bin/vi"]);
+    /// ```
+    pub fn iter_in<'a>(&'a self, arena: &'a ValueArena<String>) -> OrdStringSetIter<'a> {
+        OrdStringSetIter::new(self, arena, 0, self.len)
+    }
+
+    /// An iterator over the members that start with `prefix`.  The members that are iterated
+    /// over are stored in `arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ord_set_iter_set_ops::ValueArena;
+    /// use ord_string_set::OrdStringSet;
+    ///
+    /// let urls = OrdStringSet::from_iter((0..100).map(|i| format!("https://example.com/{i:03}")));
+    /// let arena = ValueArena::default();
+    /// let iter = urls.with_prefix_in("https://example.com/04", &arena);
+    /// assert_eq!(iter.len(), 10);
+    /// assert_eq!(iter.last(), Some(&"https://example.com/049".to_string()));
+    /// assert_eq!(arena.len(), 10);
+    /// ```
+    pub fn with_prefix_in<'a>(
+        &'a self,
+        prefix: &str,
+        arena: &'a ValueArena<String>,
+    ) -> OrdStringSetIter<'a> {
+        let (start, end) = self.prefix_range(prefix.as_bytes());
+        OrdStringSetIter::new(self, arena, start, end)
+    }
+
+    // The indices of the first member starting with `prefix` and of the first one after them.
+    fn prefix_range(&self, prefix: &[u8]) -> (usize, usize) {
+        (
+            self.partition_point(|key| key < prefix),
+            // NB: the members starting with `prefix` immediately follow those less than it
+            self.partition_point(|key| key < prefix || key.starts_with(prefix)),
+        )
+    }
+}
+
+fn to_string(key: &[u8]) -> String {
+    // NB: keys are only split (at shared prefixes) when encoding and are whole when decoded
+    String::from_utf8(key.to_vec()).expect("keys are valid UTF-8")
+}
+
+impl Default for OrdStringSet {
+    fn default() -> Self {
+        Self::empty_set()
+    }
+}
+
+impl Clone for OrdStringSet {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            restarts: self.restarts.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl PartialEq for OrdStringSet {
+    fn eq(&self, other: &Self) -> bool {
+        // NB: the encoding is canonical
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for OrdStringSet {}
+
+impl fmt::Debug for OrdStringSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.values()).finish()
+    }
+}
+
+impl<S: AsRef<str> + Ord> FromIterator<S> for OrdStringSet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut members: Vec<S> = iter.into_iter().collect();
+        members.sort_unstable();
+        members.dedup();
+        Self::from_sorted(members)
+    }
+}
+
+impl<S: AsRef<str> + Ord, const N: usize> From<[S; N]> for OrdStringSet {
+    fn from(members: [S; N]) -> Self {
+        Self::from_iter(members)
+    }
+}
+
+impl From<&OrdListSet<String>> for OrdStringSet {
+    fn from(list: &OrdListSet<String>) -> Self {
+        Self::from_sorted(list.iter())
+    }
+}
+
+impl From<&OrdStringSet> for OrdListSet<String> {
+    /// Create an OrdListSet<String> from the members of an OrdStringSet.
+    ///
+    /// Example:
+    /// ```
+    /// use ord_list_set::OrdListSet;
+    /// use ord_string_set::OrdStringSet;
+    ///
+    /// let list = OrdListSet::<String>::from_iter(["a/b", "a/b/c", "a/d"].map(String::from));
+    /// let strings = OrdStringSet::from(&list);
+    /// assert_eq!(OrdListSet::from(&strings), list);
+    /// ```
+    fn from(set: &OrdStringSet) -> Self {
+        set.values().collect()
+    }
+}
+
+/// An iterator over (a range of) the members of an `OrdStringSet`.  Seeks binary search the
+/// restart points.
+pub struct OrdStringSetIter<'a> {
+    set: &'a OrdStringSet,
+    arena: &'a ValueArena<String>,
+    // The index of the next member and the index after the last member to be visited
+    index: usize,
+    end: usize,
+    // The cursor and the index of the member in its buffer (if any)
+    cursor: Cursor<'a>,
+    decoded: Option<usize>,
+    // The next member (once it has been stored in the arena)
+    item: Option<&'a String>,
+}
+
+impl<'a> Clone for OrdStringSetIter<'a> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            arena: self.arena,
+            index: self.index,
+            end: self.end,
+            cursor: self.cursor.clone(),
+            decoded: self.decoded,
+            item: self.item,
+        }
+    }
+}
+
+impl<'a> OrdStringSetIter<'a> {
+    fn new(set: &'a OrdStringSet, arena: &'a ValueArena<String>, index: usize, end: usize) -> Self {
+        Self {
+            set,
+            arena,
+            index,
+            end,
+            cursor: Cursor::new(&set.bytes, 0),
+            decoded: None,
+            item: None,
+        }
+    }
+
+    // The next member (decoding forwards from the cursor's member when it's in range and
+    // from the preceding restart point otherwise).
+    fn current(&mut self) -> Option<&[u8]> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index;
+        let first = match self.decoded {
+            Some(decoded) if decoded <= index && index - decoded < RESTART_INTERVAL => decoded + 1,
+            _ => {
+                let restart = index / RESTART_INTERVAL;
+                self.cursor.restart(self.set.restarts[restart]);
+                restart * RESTART_INTERVAL
+            }
+        };
+        for _ in first..=index {
+            self.cursor.advance();
+        }
+        self.decoded = Some(index);
+        Some(&self.cursor.key)
+    }
+
+    // Move (forwards only) to the member at `index`.
+    fn seek(&mut self, index: usize) {
+        if index > self.index {
+            self.index = index;
+            self.item = None;
+        }
+    }
+}
+
+impl<'a> Iterator for OrdStringSetIter<'a> {
+    type Item = &'a String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.peep()?;
+        self.index += 1;
+        self.item = None;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for OrdStringSetIter<'a> {}
+
+impl<'a> PeepAdvanceIter<'a, String> for OrdStringSetIter<'a> {
+    fn peep(&mut self) -> Option<&'a String> {
+        if self.index >= self.end {
+            return None;
+        }
+        if self.item.is_none() {
+            let member = to_string(self.current()?);
+            self.item = Some(self.arena.alloc(member));
+        }
+        self.item
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, detailed: bool) -> fmt::Result {
+        if detailed {
+            write!(
+                f,
+                "OrdStringSetIter[pos={}, remaining={}]",
+                self.index,
+                self.len()
+            )
+        } else {
+            write!(f, "OrdStringSetIter[len={}]", self.set.len)
+        }
+    }
+
+    fn has_fast_advance(&self) -> bool {
+        true
+    }
+
+    fn restrict_end(&mut self, end: Bound<&String>) -> bool {
+        let end = match end {
+            Bound::Included(end) => self.set.partition_point(|key| key <= end.as_bytes()),
+            Bound::Excluded(end) => self.set.partition_point(|key| key < end.as_bytes()),
+            Bound::Unbounded => self.end,
+        };
+        self.end = self.end.min(end);
+        true
+    }
+
+    /// Advance this iterator to the next member at or after the given string.  Implementation
+    /// binary searches the restart points and then decodes (only) the target's block.
+    ///
+    /// Example
+    /// ```
+    /// use ord_set_iter_set_ops::{PeepAdvanceIter, ValueArena};
+    /// use ord_string_set::OrdStringSet;
+    ///
+    /// let set = OrdStringSet::from_iter((0..1000).map(|i| format!("key{i:04}")));
+    /// let arena = ValueArena::default();
+    /// let mut iter = set.iter_in(&arena);
+    /// iter.advance_until(&"key0500x".to_string());
+    /// assert_eq!(iter.next(), Some(&"key0501".to_string()));
+    /// iter.advance_until(&"key0010".to_string());
+    /// assert_eq!(iter.next(), Some(&"key0502".to_string()));
+    /// iter.advance_after(&"key0998".to_string());
+    /// assert_eq!(iter.next(), Some(&"key0999".to_string()));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    fn advance_until(&mut self, target: &String) {
+        let target = target.as_bytes();
+        // Make sure we don't go backwards
+        let index = self.set.partition_point(|key| key < target);
+        self.seek(index);
+    }
+
+    fn advance_after(&mut self, target: &String) {
+        let target = target.as_bytes();
+        // Make sure we don't go backwards
+        let index = self.set.partition_point(|key| key <= target);
+        self.seek(index);
+    }
+}
+
+impl<'a> OrdSetIterSetOpsIterator<'a, String> for OrdStringSetIter<'a> {}
+
+impl<'a> fmt::Debug for OrdStringSetIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, true)
+    }
+}
+
+impl<'a> fmt::Display for OrdStringSetIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> Vec<String> {
+        let mut paths = vec![];
+        for dir in ["bin", "lib", "share/doc", "share/man"] {
+            for i in 0..50 {
+                paths.push(format!("/usr/{dir}/file{i:02}"));
+            }
+        }
+        paths
+    }
+
+    #[test]
+    fn round_trip() {
+        let list = OrdListSet::<String>::from(paths());
+        let set = OrdStringSet::from(&list);
+        assert_eq!(set.len(), 200);
+        assert!(set.encoded_size() < list.iter().map(|path| path.len()).sum::<usize>() / 2);
+        assert_eq!(OrdListSet::from(&set), list);
+        let arena = ValueArena::default();
+        assert!(set.iter_in(&arena).eq(list.iter()));
+        assert_eq!(arena.len(), 200);
+        assert!(set.values().eq(list.iter().cloned()));
+        assert_eq!(set, OrdStringSet::from_iter(paths().iter().rev()));
+        assert!(set.contains("/usr/bin/file00") && set.contains("/usr/share/man/file49"));
+        assert!(!set.contains("/usr/bin") && !set.contains("/usr/zzz") && !set.contains(""));
+        assert!(OrdStringSet::empty_set().iter_in(&arena).next().is_none());
+        assert!(OrdStringSet::empty_set()
+            .values_with_prefix("")
+            .next()
+            .is_none());
+        assert!(!OrdStringSet::empty_set().contains("a"));
+    }
+
+    #[test]
+    fn prefixes_and_seeks() {
+        let set = OrdStringSet::from_iter(paths());
+        let arena = ValueArena::default();
+        assert_eq!(set.with_prefix_in("/usr/share/", &arena).len(), 100);
+        assert_eq!(set.with_prefix_in("/usr/lib/file1", &arena).len(), 10);
+        assert_eq!(set.with_prefix_in("/usr/local/", &arena).len(), 0);
+        assert_eq!(set.with_prefix_in("", &arena).len(), 200);
+        assert!(set
+            .values_with_prefix("/usr/lib/file1")
+            .eq((10..20).map(|i| format!("/usr/lib/file{i}"))));
+        assert_eq!(set.values_with_prefix("/usr/local/").count(), 0);
+        let mut iter = set.with_prefix_in("/usr/share/doc/", &arena);
+        iter.advance_until(&"/usr/share/doc/file4".to_string());
+        assert_eq!(iter.next(), Some(&"/usr/share/doc/file40".to_string()));
+        assert_eq!(
+            format!("{iter:?}"),
+            "OrdStringSetIter[pos=141, remaining=9]"
+        );
+        iter.advance_after(&"/usr/share/doc/file48".to_string());
+        assert_eq!(iter.next(), Some(&"/usr/share/doc/file49".to_string()));
+        assert_eq!(iter.next(), None);
+        assert_eq!(arena.len(), 2);
+        let iter = set
+            .iter_in(&arena)
+            .restrict("/usr/lib/file48".to_string()..="/usr/share/doc/file01".to_string());
+        assert!(iter.is_pushed_down());
+        assert_eq!(iter.count(), 4);
+    }
+}